@@ -1,125 +1,670 @@
-use std::ops::{Add, AddAssign, Mul, MulAssign, Sub};
+// Implements CipherTextType, the encrypted scalar which is used as the element type of matrices
+// and neural networks.
+//
+// Every operation is implemented as a checked_*() method which returns a CryptoError if the
+// ciphertexts can't be operated on together, e.g. because they were created with different keys.
+// The std::ops traits are implemented on top of them, so that Matrix<CipherTextType> can use the
+// generic tensor operations. Since the traits can't return a Result, they panic on such errors.
+//
+// The server key holds the bootstrapping keys, which take up megabytes, so it's shared between all
+// ciphertexts created with it through an Arc instead of being copied into every one of them.
+//
+// The server key isn't serialized with the ciphertexts, since it's shared and much larger than
+// them. Whoever deserializes ciphertexts has to attach the server key again before operating on
+// them.
+//
+// A default CipherTextType doesn't hold a ciphertext. It behaves like an encrypted zero, so that
+// matrices created with Matrix::new() can be used in sums.
+
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
 use tfhe::shortint::prelude::*;
+use crate::cryptography::errors::CryptoError;
+use crate::cryptography::key_gen::PublicEncryptionKey;
+
 
+/// How the operations deal with ciphertexts whose carry buffer is full or whose noise is too big.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BudgetMode {
+    /// The operands are bootstrapped whenever an operation would exceed the budget, so the
+    /// results are always correct.
+    #[default]
+    Managed,
+    /// The operations are computed as they are. This is faster, but the results are wrong once
+    /// the budget is exceeded, which is_within_budget() can tell.
+    Unchecked,
+}
 
 /// This struct is here to allow fast and easy flexibility and to limit the future problems
 /// of using concrete_integer::RadixCiphertext directly.
 ///
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct CipherTextType{
     pub CipherTxt: Option<Ciphertext>,
-    pub ServerKey: Option<ServerKey>,
-    pub Modulus: Option<u64>
+    #[serde(skip)]
+    pub ServerKey: Option<Arc<ServerKey>>,
+    pub Modulus: Option<u64>,
+    // The noise in multiples of the noise of a fresh encryption. It isn't serialized, since the
+    // server can't trust the sender, set_server_key() sets it instead.
+    #[serde(skip)]
+    pub NoiseLevel: usize,
+    #[serde(skip)]
+    pub Mode: BudgetMode,
+}
+
+/// Returns the highest noise level at which a ciphertext can still be bootstrapped correctly. The
+/// parameter sets are chosen so that a sum of as many fresh ciphertexts as fit in the carry buffer
+/// can be, i.e. (message_modulus * carry_modulus - 1) / (message_modulus - 1).
+fn max_noise_level(ciphertext: &Ciphertext) -> usize {
+    let (message_modulus, carry_modulus) = (ciphertext.message_modulus.0, ciphertext.carry_modulus.0);
+    (message_modulus * carry_modulus - 1) / (message_modulus - 1).max(1)
+}
+
+/// A copy of a ciphertext together with its noise level, which the operations modify.
+struct Operand {
+    ciphertext: Ciphertext,
+    noise_level: usize,
+}
+
+impl Operand {
+    /// Empties the carry buffer and resets the noise with a bootstrap.
+    fn bootstrap(&mut self, server_key: &ServerKey) {
+        server_key.message_extract_assign(&mut self.ciphertext);
+        self.noise_level = 1;
+    }
+
+    /// Returns true if a bootstrap wouldn't make any difference.
+    fn is_clean(&self) -> bool {
+        self.noise_level <= 1 && self.ciphertext.degree.0 < self.ciphertext.message_modulus.0
+    }
+
+    /// Replaces the message m with func(m) with a programmable bootstrap.
+    fn lookup<F>(&mut self, server_key: &ServerKey, func: F) where F: Fn(u64) -> u64 {
+        let modulus = self.ciphertext.message_modulus.0 as u64;
+        // The table is evaluated on the carry buffer as well, which holds the multiples of the
+        // modulus
+        let accumulator = server_key.generate_accumulator(|x| func(x % modulus) % modulus);
+        server_key.keyswitch_programmable_bootstrap_assign(&mut self.ciphertext, &accumulator);
+        self.noise_level = 1;
+    }
+}
+
+/// Bootstraps the operand if fits() returns false. Returns CryptoError::BudgetExceeded if the
+/// operation doesn't fit even then.
+fn fit_operand<F>(server_key: &ServerKey, operand: &mut Operand, fits: F) -> Result<(), CryptoError> where F: Fn(&Operand) -> bool {
+    if fits(operand) {
+        return Ok(());
+    }
+    if operand.is_clean() {
+        return Err(CryptoError::BudgetExceeded);
+    }
+    operand.bootstrap(server_key);
+    match fits(operand) {
+        true => Ok(()),
+        false => Err(CryptoError::BudgetExceeded),
+    }
+}
+
+/// Bootstraps the operands until fits() returns true, starting with the one with the larger
+/// degree, which is the same as what the smart operations of the server key do. Returns
+/// CryptoError::BudgetExceeded if the operation doesn't fit even with both of them clean.
+fn fit_operands<F>(server_key: &ServerKey, lhs: &mut Operand, rhs: &mut Operand, fits: F) -> Result<(), CryptoError> where F: Fn(&Operand, &Operand) -> bool {
+    while !fits(lhs, rhs) {
+        let bootstrap_lhs = match (lhs.is_clean(), rhs.is_clean()) {
+            (true, true) => return Err(CryptoError::BudgetExceeded),
+            (false, true) => true,
+            (true, false) => false,
+            (false, false) => (lhs.ciphertext.degree.0, lhs.noise_level) >= (rhs.ciphertext.degree.0, rhs.noise_level),
+        };
+        match bootstrap_lhs {
+            true => lhs.bootstrap(server_key),
+            false => rhs.bootstrap(server_key),
+        }
+    }
+    Ok(())
 }
 
-/// This impl block implements a basic constructor and a is_def() method. The is_def() method is
-/// here since operations on default values return None, because there isn't a default value for
-/// Ciphertext or for ServerKey.
+/// This impl block implements the constructors and a is_def() method. The is_def() method is
+/// here since there isn't a default value for Ciphertext or for ServerKey, so default values
+/// don't hold a ciphertext.
 ///
 impl CipherTextType{
-    fn new(_CipherTxt: Ciphertext, _ServerKey: ServerKey, _Modulus: u64) -> CipherTextType{
+    /// Wraps a fresh ciphertext, i.e. one which was just encrypted or bootstrapped.
+    pub fn new(_CipherTxt: Ciphertext, _ServerKey: Arc<ServerKey>, _Modulus: u64) -> CipherTextType{
         CipherTextType{
-            CipherTxt: Some(_CipherTxt),
             ServerKey: Some(_ServerKey),
-            Modulus: Some(_Modulus)
+            ..CipherTextType::new_without_key(_CipherTxt, _Modulus)
+        }
+    }
+
+    /// Wraps a freshly encrypted ciphertext without a server key, e.g. one which gets sent to the
+    /// server, which attaches its own key with set_server_key().
+    pub fn new_without_key(_CipherTxt: Ciphertext, _Modulus: u64) -> CipherTextType{
+        CipherTextType{
+            CipherTxt: Some(_CipherTxt),
+            ServerKey: None,
+            Modulus: Some(_Modulus),
+            NoiseLevel: 1,
+            Mode: BudgetMode::Managed,
+        }
+    }
+
+    /// Encrypts value with the client key. The result can be operated on with the server key,
+    /// which is shared with all other ciphertexts encrypted with the same Arc.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::ciphtxt::CipherTextType;
+    /// let (client_key, server_key) = gen_keys(PARAM_MESSAGE_2_CARRY_2);
+    /// let server_key = Arc::new(server_key);
+    ///
+    /// let lhs = CipherTextType::encrypt(3, &client_key, &server_key);
+    /// let rhs = CipherTextType::encrypt(2, &client_key, &server_key);
+    ///
+    /// let result = lhs.checked_add(&rhs).unwrap();
+    /// assert_eq!(result.decrypt(&client_key), 1); // (3 + 2) % 4
+    /// ```
+    pub fn encrypt(value: u64, client_key: &ClientKey, server_key: &Arc<ServerKey>) -> CipherTextType {
+        let modulus = client_key.parameters.message_modulus.0 as u64;
+        CipherTextType::new(client_key.encrypt(value), server_key.clone(), modulus)
+    }
+
+    /// Encrypts value with a public key, so that it can be done by parties which don't hold the
+    /// client key. The result doesn't hold a server key, whoever operates on it has to attach one
+    /// with set_server_key().
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::ciphtxt::CipherTextType;
+    /// use Cryptonic::cryptography::key_gen::gen_compressed_public_key;
+    /// let (client_key, server_key) = gen_keys(PARAM_MESSAGE_2_CARRY_2);
+    /// let public_key = gen_compressed_public_key(&client_key);
+    ///
+    /// let mut value = CipherTextType::encrypt_with_public_key(3, &public_key);
+    /// value.set_server_key(&Arc::new(server_key)).unwrap();
+    /// let result = value.checked_scalar_add(2).unwrap();
+    /// assert_eq!(result.decrypt(&client_key), 1); // (3 + 2) % 4
+    /// ```
+    pub fn encrypt_with_public_key<K: PublicEncryptionKey>(value: u64, public_key: &K) -> CipherTextType {
+        let modulus = public_key.parameters().message_modulus.0 as u64;
+        CipherTextType::new_without_key(public_key.encrypt(value), modulus)
+    }
+
+    /// Decrypts the ciphertext with the client key. A default value behaves like an encrypted
+    /// zero, so it decrypts to 0.
+    pub fn decrypt(&self, client_key: &ClientKey) -> u64 {
+        match &self.CipherTxt {
+            Some(ciphertext) => client_key.decrypt(ciphertext),
+            None => 0,
+        }
+    }
+
+    /// Returns true if this is a default value, i.e. it doesn't hold a ciphertext.
+    pub fn is_def(&self) -> bool {
+        self.CipherTxt.is_none()
+    }
+
+    /// Attaches the server key to a ciphertext, e.g. after it was deserialized. Returns
+    /// CryptoError::ModulusMismatch if the ciphertext was encrypted with another message modulus.
+    ///
+    /// The noise of a ciphertext from somewhere else is unknown, so it's assumed to be as high as
+    /// possible, i.e. the next operation in BudgetMode::Managed bootstraps it first.
+    pub fn set_server_key(&mut self, server_key: &Arc<ServerKey>) -> Result<(), CryptoError> {
+        if !self.is_def() && self.Modulus != Some(server_key.message_modulus.0 as u64) {
+            return Err(CryptoError::ModulusMismatch);
+        }
+        self.ServerKey = Some(server_key.clone());
+        self.NoiseLevel = self.NoiseLevel.max(self.max_noise_level());
+        Ok(())
+    }
+
+    /// Returns the server key and a copy of the ciphertext with its noise level, which the
+    /// operations can modify.
+    fn operand(&self) -> Result<(&ServerKey, Operand), CryptoError> {
+        match (&self.ServerKey, &self.CipherTxt) {
+            (Some(server_key), Some(ciphertext)) => Ok((server_key, Operand { ciphertext: ciphertext.clone(), noise_level: self.NoiseLevel })),
+            _ => Err(CryptoError::MissingKey),
+        }
+    }
+
+    /// Checks that self and rhs can be operated on together. Ciphertexts sharing the same Arc
+    /// are compared without looking at the keys, which is the case for almost all operations.
+    fn check_compatible(&self, rhs: &CipherTextType) -> Result<(), CryptoError> {
+        if self.Modulus != rhs.Modulus {
+            return Err(CryptoError::ModulusMismatch);
+        }
+        let same_key = match (&self.ServerKey, &rhs.ServerKey) {
+            (Some(lhs_key), Some(rhs_key)) => Arc::ptr_eq(lhs_key, rhs_key) || lhs_key == rhs_key,
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_key {
+            return Err(CryptoError::KeyMismatch);
+        }
+        Ok(())
+    }
+
+    /// Returns a CipherTextType with the same key and modulus as self, holding the operand.
+    fn with_operand(&self, operand: Operand, mode: BudgetMode) -> CipherTextType {
+        CipherTextType {
+            CipherTxt: Some(operand.ciphertext),
+            ServerKey: self.ServerKey.clone(),
+            Modulus: self.Modulus,
+            NoiseLevel: operand.noise_level,
+            Mode: mode,
         }
     }
-    fn is_def(&self) -> bool {
-        if self.CipherTxt.is_none() || self.ServerKey.is_none() || self.Modulus.is_none() {
-            return true;
+
+    /// The result of an operation is only unchecked if both operands are.
+    fn combined_mode(&self, rhs: &CipherTextType) -> BudgetMode {
+        match (self.Mode, rhs.Mode) {
+            (BudgetMode::Unchecked, BudgetMode::Unchecked) => BudgetMode::Unchecked,
+            _ => BudgetMode::Managed,
         }
-        false
     }
 }
 
-impl Clone for CipherTextType{
-    fn clone(&self) -> Self {
+// Implements the noise and carry budget. Every ciphertext tracks the degree of its message, i.e.
+// the biggest value it can hold including the carries, and its noise level. In BudgetMode::Managed
+// the operations bootstrap the operands whenever the result would overflow the carry buffer or
+// have too much noise, so the results are always correct.
+impl CipherTextType {
+    /// Returns the noise level in multiples of the noise of a fresh encryption. Default values
+    /// and trivial ciphertexts have no noise.
+    pub fn noise_level(&self) -> usize {
+        self.NoiseLevel
+    }
+
+    /// Returns the biggest value the ciphertext can hold including the carries, or 0 for a default
+    /// value.
+    pub fn degree(&self) -> usize {
+        self.CipherTxt.as_ref().map_or(0, |ciphertext| ciphertext.degree.0)
+    }
+
+    /// Returns the highest noise level at which the ciphertext can still be bootstrapped.
+    pub fn max_noise_level(&self) -> usize {
+        self.CipherTxt.as_ref().map_or(0, max_noise_level)
+    }
+
+    /// Returns true if neither the carry buffer has overflowed nor the noise is too big, i.e. if
+    /// the ciphertext still decrypts correctly. This is always the case in BudgetMode::Managed.
+    pub fn is_within_budget(&self) -> bool {
+        match &self.CipherTxt {
+            Some(ciphertext) => ciphertext.degree.0 < ciphertext.message_modulus.0 * ciphertext.carry_modulus.0 && self.NoiseLevel <= max_noise_level(ciphertext),
+            None => true,
+        }
+    }
+
+    /// Empties the carry buffer and resets the noise, so that the result is as fresh as a new
+    /// encryption. A default value stays the same.
+    pub fn bootstrap(&self) -> Result<CipherTextType, CryptoError> {
         if self.is_def() {
-            CipherTextType::new(self.CipherTxt.clone().unwrap(), self.ServerKey.clone().unwrap(), self.Modulus.clone().unwrap())
+            return Ok(self.clone());
         }
-        else{
-            // Error
-            CipherTextType::default()
+        let (server_key, mut operand) = self.operand()?;
+        operand.bootstrap(server_key);
+        Ok(self.with_operand(operand, self.Mode))
+    }
+
+    /// Sets the budget mode of the ciphertext. Results of operations are only unchecked if all of
+    /// their operands are.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::ciphtxt::{BudgetMode, CipherTextType};
+    /// let (client_key, server_key) = gen_keys(PARAM_MESSAGE_2_CARRY_2);
+    /// let mut value = CipherTextType::encrypt(1, &client_key, &Arc::new(server_key));
+    /// value.set_budget_mode(BudgetMode::Unchecked);
+    ///
+    /// let mut sum = value.clone();
+    /// for _ in 0..7 {
+    ///     sum = sum.checked_add(&value).unwrap();
+    /// }
+    /// assert!(!sum.is_within_budget()); // the degree is 8 * 3 = 24, which doesn't fit in 4 bits
+    /// ```
+    pub fn set_budget_mode(&mut self, mode: BudgetMode) {
+        self.Mode = mode;
+    }
+
+    /// Computes op on the operands, bootstrapping them first until fits() returns true in
+    /// BudgetMode::Managed.
+    fn binary_op<F, O>(&self, rhs: &CipherTextType, fits: F, op: O) -> Result<CipherTextType, CryptoError>
+    where F: Fn(&ServerKey, &Operand, &Operand) -> bool, O: Fn(&ServerKey, &Operand, &Operand) -> Operand {
+        self.check_compatible(rhs)?;
+        let (server_key, mut lhs_operand) = self.operand()?;
+        let (_, mut rhs_operand) = rhs.operand()?;
+        let mode = self.combined_mode(rhs);
+        if mode == BudgetMode::Managed {
+            fit_operands(server_key, &mut lhs_operand, &mut rhs_operand, |lhs, rhs| fits(server_key, lhs, rhs))?;
         }
+        Ok(self.with_operand(op(server_key, &lhs_operand, &rhs_operand), mode))
     }
-}
 
-/// Default trait implementation. CipherTxt and ServerKey are set to None which is then checked
-/// by the ops traits and return None if any of the two are None.
-impl Default for CipherTextType{
-    fn default() -> Self {
-        CipherTextType{
-            CipherTxt: None,
-            ServerKey: None,
-            Modulus: None
+    /// Computes op on self, bootstrapping it first if fits() returns false in
+    /// BudgetMode::Managed. If it doesn't fit even then, the lookup table is evaluated instead.
+    fn unary_op<F, O, L>(&self, fits: F, op: O, lookup: L) -> Result<CipherTextType, CryptoError>
+    where F: Fn(&ServerKey, &Operand) -> bool, O: Fn(&ServerKey, &Operand) -> Operand, L: Fn(u64) -> u64 {
+        let (server_key, mut operand) = self.operand()?;
+        if self.Mode == BudgetMode::Managed {
+            if let Err(CryptoError::BudgetExceeded) = fit_operand(server_key, &mut operand, |operand| fits(server_key, operand)) {
+                operand.lookup(server_key, lookup);
+                return Ok(self.with_operand(operand, self.Mode));
+            }
         }
+        Ok(self.with_operand(op(server_key, &operand), self.Mode))
     }
 }
 
-/// Implements the Add trait
-impl Add for CipherTextType {
-    type Output = Option<CipherTextType>;
+// Implements the checked operations. All of them work modulo the message modulus.
+impl CipherTextType {
+    /// Adds rhs to self.
+    pub fn checked_add(&self, rhs: &CipherTextType) -> Result<CipherTextType, CryptoError> {
+        if rhs.is_def() {
+            return Ok(self.clone());
+        }
+        if self.is_def() {
+            return Ok(rhs.clone());
+        }
+        self.binary_op(
+            rhs,
+            |server_key, lhs, rhs| server_key.is_add_possible(&lhs.ciphertext, &rhs.ciphertext)
+                && lhs.noise_level.saturating_add(rhs.noise_level) <= max_noise_level(&lhs.ciphertext),
+            |server_key, lhs, rhs| Operand {
+                ciphertext: server_key.unchecked_add(&lhs.ciphertext, &rhs.ciphertext),
+                noise_level: lhs.noise_level.saturating_add(rhs.noise_level),
+            },
+        )
+    }
 
-    fn add(self, rhs: Self) -> Self::Output {
-        if !self.is_def() && !rhs.is_def() {
-            let _ciphertext = self.ServerKey.clone().unwrap().unchecked_add(&self.CipherTxt.unwrap(), &rhs.CipherTxt.unwrap());
-            return Some(CipherTextType::new(_ciphertext, self.ServerKey.clone().unwrap(), self.Modulus.clone().unwrap()));
+    /// Subtracts rhs from self.
+    pub fn checked_sub(&self, rhs: &CipherTextType) -> Result<CipherTextType, CryptoError> {
+        if rhs.is_def() {
+            return Ok(self.clone());
         }
-        else if !self.is_def() && rhs.is_def() {
-            return Some(self.clone());
+        if self.is_def() {
+            return rhs.checked_neg();
         }
-        else if self.is_def() && !rhs.is_def() {
-            return Some(rhs.clone());
+        self.binary_op(
+            rhs,
+            |server_key, lhs, rhs| server_key.is_sub_possible(&lhs.ciphertext, &rhs.ciphertext)
+                && lhs.noise_level.saturating_add(rhs.noise_level) <= max_noise_level(&lhs.ciphertext),
+            |server_key, lhs, rhs| Operand {
+                ciphertext: server_key.unchecked_sub(&lhs.ciphertext, &rhs.ciphertext),
+                noise_level: lhs.noise_level.saturating_add(rhs.noise_level),
+            },
+        )
+    }
+
+    /// Multiplies self by rhs. Only the part of the product which fits in the message modulus is
+    /// kept. The product is computed with a bootstrap, so the result is fresh.
+    pub fn checked_mul(&self, rhs: &CipherTextType) -> Result<CipherTextType, CryptoError> {
+        if self.is_def() || rhs.is_def() {
+            return Ok(CipherTextType::default());
         }
-        None
+        // The smart multiplication takes care of the carries itself, since the algorithm depends
+        // on the parameters, but it doesn't know about the noise
+        self.binary_op(
+            rhs,
+            |_, lhs, rhs| lhs.noise_level <= 1 && rhs.noise_level <= 1,
+            |server_key, lhs, rhs| Operand {
+                ciphertext: server_key.smart_mul_lsb(&mut lhs.ciphertext.clone(), &mut rhs.ciphertext.clone()),
+                noise_level: 1,
+            },
+        )
+    }
+
+    /// Returns the additive inverse of self.
+    pub fn checked_neg(&self) -> Result<CipherTextType, CryptoError> {
+        if self.is_def() {
+            return Ok(CipherTextType::default());
+        }
+        self.unary_op(
+            |server_key, operand| server_key.is_neg_possible(&operand.ciphertext),
+            |server_key, operand| Operand {
+                ciphertext: server_key.unchecked_neg(&operand.ciphertext),
+                noise_level: operand.noise_level,
+            },
+            |x| x.wrapping_neg(),
+        )
     }
-}
 
-/// Implements the Mul trait
-impl Mul for CipherTextType {
-    type Output = Option<CipherTextType>;
+    /// Adds the plaintext scalar to self. Negative scalars are added modulo the message modulus,
+    /// e.g. adding -1 is the same as adding modulus - 1. A default value has no key to encrypt the
+    /// scalar with, so it returns CryptoError::MissingKey.
+    pub fn checked_scalar_add(&self, scalar: i64) -> Result<CipherTextType, CryptoError> {
+        let scalar = self.reduce_scalar(scalar)?;
+        match u8::try_from(scalar) {
+            Ok(scalar) => self.unary_op(
+                |server_key, operand| server_key.is_scalar_add_possible(&operand.ciphertext, scalar),
+                |server_key, operand| Operand {
+                    ciphertext: server_key.unchecked_scalar_add(&operand.ciphertext, scalar),
+                    noise_level: operand.noise_level,
+                },
+                |x| x + scalar as u64,
+            ),
+            // The scalar ops only take a u8, so bigger scalars are added as trivial ciphertexts
+            Err(_) => {
+                let (server_key, _) = self.operand()?;
+                self.checked_add(&self.trivial(server_key.create_trivial(scalar)))
+            }
+        }
+    }
 
-    fn mul(self, rhs: Self) -> Self::Output {
-        if !self.is_def() || !rhs.is_def() {
-            let _ciphertext = self.ServerKey.clone().unwrap().smart_mul_lsb(&mut self.CipherTxt.unwrap(), &mut rhs.CipherTxt.unwrap());
+    /// Subtracts the plaintext scalar from self. A default value has no key to encrypt the scalar
+    /// with, so it returns CryptoError::MissingKey.
+    pub fn checked_scalar_sub(&self, scalar: i64) -> Result<CipherTextType, CryptoError> {
+        let scalar = self.reduce_scalar(scalar)?;
+        let modulus = self.Modulus.ok_or(CryptoError::MissingKey)?;
+        self.checked_scalar_add(((modulus - scalar) % modulus) as i64)
+    }
 
-            Some(CipherTextType::new(_ciphertext, self.ServerKey.clone().unwrap(), self.Modulus.clone().unwrap()));
+    /// Multiplies self by the plaintext scalar. Negative scalars are reduced modulo the message
+    /// modulus, so multiplying by -1 negates the value.
+    pub fn checked_scalar_mul(&self, scalar: i64) -> Result<CipherTextType, CryptoError> {
+        if self.is_def() {
+            return Ok(CipherTextType::default());
+        }
+        let scalar = self.reduce_scalar(scalar)?;
+        if let Ok(scalar) = u8::try_from(scalar) {
+            // The noise grows with the scalar as well. unchecked_scalar_mul is used since
+            // smart_scalar_mul multiplies the degree twice.
+            return self.unary_op(
+                |server_key, operand| server_key.is_scalar_mul_possible(&operand.ciphertext, scalar)
+                    && operand.noise_level.saturating_mul(scalar as usize) <= max_noise_level(&operand.ciphertext),
+                |server_key, operand| Operand {
+                    ciphertext: server_key.unchecked_scalar_mul(&operand.ciphertext, scalar),
+                    noise_level: operand.noise_level.saturating_mul(scalar as usize),
+                },
+                |x| x * scalar as u64,
+            );
+        }
 
+        // The scalar ops only take a u8, so bigger scalars are multiplied digit by digit in base
+        // 16 with Horner's method: x * (16 * a + b) = (x * a) * 16 + x * b
+        let mut digits: Vec<i64> = Vec::new();
+        let mut rest = scalar;
+        while rest > 0 {
+            digits.push((rest % 16) as i64);
+            rest /= 16;
+        }
+        let (server_key, _) = self.operand()?;
+        let mut result = self.trivial(server_key.create_trivial(0));
+        for digit in digits.iter().rev() {
+            result = result.checked_scalar_mul(16)?.checked_add(&self.checked_scalar_mul(*digit)?)?;
         }
-        None
+        Ok(result)
     }
-}
 
-/// Implements the Sub trait
-impl Sub for CipherTextType {
-    type Output = Option<CipherTextType>;
+    /// Evaluates the lookup table func on the encrypted message with a programmable bootstrap.
+    /// func gets the message in the range [0, modulus) and its result is reduced modulo the
+    /// message modulus. This is how non-linear functions are computed, and since the bootstrap
+    /// resets the noise and empties the carry buffer, the result is as fresh as a new encryption.
+    ///
+    /// A default value is an encrypted zero without a key, so it returns CryptoError::MissingKey.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::ciphtxt::CipherTextType;
+    /// let (client_key, server_key) = gen_keys(PARAM_MESSAGE_2_CARRY_2);
+    /// let value = CipherTextType::encrypt(3, &client_key, &Arc::new(server_key));
+    ///
+    /// let squared = value.apply_lookup_table(|x| x * x).unwrap();
+    /// assert_eq!(squared.decrypt(&client_key), 1); // 9 % 4
+    /// ```
+    pub fn apply_lookup_table<F>(&self, func: F) -> Result<CipherTextType, CryptoError> where F: Fn(u64) -> u64 {
+        let (server_key, mut operand) = self.operand()?;
+        operand.lookup(server_key, func);
+        Ok(self.with_operand(operand, self.Mode))
+    }
 
-    fn sub(self, rhs: Self) -> Self::Output {
-        if !self.is_def() || !rhs.is_def() {
-            let _ciphertext = self.ServerKey.clone().unwrap().unchecked_sub(&self.CipherTxt.unwrap(), &rhs.CipherTxt.unwrap());
+    /// Wraps a trivial ciphertext, which has no noise, with the same key, modulus and budget mode
+    /// as self.
+    fn trivial(&self, ciphertext: Ciphertext) -> CipherTextType {
+        self.with_operand(Operand { ciphertext, noise_level: 0 }, self.Mode)
+    }
 
-            Some(CipherTextType::new(_ciphertext, self.ServerKey.clone().unwrap(), self.Modulus.clone().unwrap()));
+    /// Reduces the scalar modulo the message modulus, so that negative scalars become positive.
+    fn reduce_scalar(&self, scalar: i64) -> Result<u64, CryptoError> {
+        match self.Modulus {
+            Some(modulus) => Ok((scalar as i128).rem_euclid(modulus as i128) as u64),
+            None => Err(CryptoError::MissingKey),
         }
-        None
     }
 }
 
-
-/// Implements the AddAssign trait
-impl AddAssign for CipherTextType {
-    fn add_assign(&mut self, rhs: Self) {
-        if !self.is_def() || !rhs.is_def() {
-            let _ciphertext = self.ServerKey.clone().unwrap().unchecked_add(&self.CipherTxt.clone().unwrap(), &rhs.CipherTxt.clone().unwrap());
-            self.CipherTxt = Some(_ciphertext);
+/// Ciphertexts can't be printed, so only their modulus is shown.
+impl Debug for CipherTextType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match (&self.CipherTxt, self.Modulus) {
+            (Some(_), Some(modulus)) => write!(f, "CipherTextType(mod {modulus})"),
+            (Some(_), None) => write!(f, "CipherTextType"),
+            (None, _) => write!(f, "CipherTextType(default)"),
         }
     }
 }
 
-/// Implements the MulAssign trait
-impl MulAssign for CipherTextType {
-    fn mul_assign(&mut self, rhs: Self) {
-        if !self.is_def() || !rhs.is_def() {
-            let _ciphertext = self.ServerKey.clone().unwrap().smart_mul_lsb(&mut self.CipherTxt.clone().unwrap(), &mut rhs.CipherTxt.clone().unwrap());
-            self.CipherTxt = Some(_ciphertext);
+/// Used by the operator traits, which can't return an error.
+pub(crate) fn unwrap_or_panic<T>(result: Result<T, CryptoError>) -> T {
+    match result {
+        Ok(result) => result,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+// Implements the operators between ciphertexts for owned and borrowed operands and the assign
+// variants. The macros are shared with the other encrypted types, e.g. RadixCipherText.
+macro_rules! impl_ciphertext_op {
+    ($ty:ty, $op_trait:ident, $op_fn:ident, $assign_trait:ident, $assign_fn:ident, $checked_fn:ident) => {
+        impl $op_trait<&$ty> for &$ty {
+            type Output = $ty;
+
+            fn $op_fn(self, rhs: &$ty) -> $ty {
+                $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(rhs))
+            }
         }
+
+        impl $op_trait for $ty {
+            type Output = $ty;
+
+            fn $op_fn(self, rhs: $ty) -> $ty {
+                $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(&rhs))
+            }
+        }
+
+        impl $assign_trait for $ty {
+            fn $assign_fn(&mut self, rhs: $ty) {
+                *self = $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(&rhs));
+            }
+        }
+    };
+}
+
+pub(crate) use impl_ciphertext_op;
+
+impl_ciphertext_op!(CipherTextType, Add, add, AddAssign, add_assign, checked_add);
+impl_ciphertext_op!(CipherTextType, Sub, sub, SubAssign, sub_assign, checked_sub);
+impl_ciphertext_op!(CipherTextType, Mul, mul, MulAssign, mul_assign, checked_mul);
+
+// Implements the operators with a plaintext scalar on either side. The neural networks use them
+// with i32 weights and biases.
+macro_rules! impl_ciphertext_scalar_op {
+    ($ty:ty, $scalar:ty, $op_trait:ident, $op_fn:ident, $assign_trait:ident, $assign_fn:ident, $checked_fn:ident) => {
+        impl $op_trait<$scalar> for $ty {
+            type Output = $ty;
+
+            fn $op_fn(self, rhs: $scalar) -> $ty {
+                $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(i64::from(rhs)))
+            }
+        }
+
+        impl $op_trait<$scalar> for &$ty {
+            type Output = $ty;
+
+            fn $op_fn(self, rhs: $scalar) -> $ty {
+                $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(i64::from(rhs)))
+            }
+        }
+
+        impl $assign_trait<$scalar> for $ty {
+            fn $assign_fn(&mut self, rhs: $scalar) {
+                *self = $crate::cryptography::ciphtxt::unwrap_or_panic(self.$checked_fn(i64::from(rhs)));
+            }
+        }
+    };
+}
+
+pub(crate) use impl_ciphertext_scalar_op;
+
+impl_ciphertext_scalar_op!(CipherTextType, u8, Add, add, AddAssign, add_assign, checked_scalar_add);
+impl_ciphertext_scalar_op!(CipherTextType, u8, Sub, sub, SubAssign, sub_assign, checked_scalar_sub);
+impl_ciphertext_scalar_op!(CipherTextType, u8, Mul, mul, MulAssign, mul_assign, checked_scalar_mul);
+impl_ciphertext_scalar_op!(CipherTextType, i32, Add, add, AddAssign, add_assign, checked_scalar_add);
+impl_ciphertext_scalar_op!(CipherTextType, i32, Sub, sub, SubAssign, sub_assign, checked_scalar_sub);
+impl_ciphertext_scalar_op!(CipherTextType, i32, Mul, mul, MulAssign, mul_assign, checked_scalar_mul);
+
+// Addition and multiplication are commutative, so the scalar can be on the left side as well
+macro_rules! impl_scalar_ciphertext_op {
+    ($ty:ty, $scalar:ty, $op_trait:ident, $op_fn:ident, $checked_fn:ident) => {
+        impl $op_trait<$ty> for $scalar {
+            type Output = $ty;
+
+            fn $op_fn(self, rhs: $ty) -> $ty {
+                $crate::cryptography::ciphtxt::unwrap_or_panic(rhs.$checked_fn(i64::from(self)))
+            }
+        }
+    };
+}
+
+pub(crate) use impl_scalar_ciphertext_op;
+
+impl_scalar_ciphertext_op!(CipherTextType, u8, Add, add, checked_scalar_add);
+impl_scalar_ciphertext_op!(CipherTextType, u8, Mul, mul, checked_scalar_mul);
+impl_scalar_ciphertext_op!(CipherTextType, i32, Add, add, checked_scalar_add);
+impl_scalar_ciphertext_op!(CipherTextType, i32, Mul, mul, checked_scalar_mul);
+
+impl Neg for CipherTextType {
+    type Output = CipherTextType;
+
+    fn neg(self) -> CipherTextType {
+        unwrap_or_panic(self.checked_neg())
+    }
+}
+
+impl Neg for &CipherTextType {
+    type Output = CipherTextType;
+
+    fn neg(self) -> CipherTextType {
+        unwrap_or_panic(self.checked_neg())
     }
 }
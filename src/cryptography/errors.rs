@@ -0,0 +1,55 @@
+use std::error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::neural_network::errors::NnetError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CryptoError {
+    MissingKey,
+    KeyMismatch,
+    ModulusMismatch,
+    InsecureParameters,
+    ParameterMismatch,
+    InvalidKeyName,
+    SerializationError,
+    IoError,
+    // Boxed, since NnetError wraps CryptoError as well
+    InferenceError(Box<NnetError>),
+    InvalidCiphertext,
+    InvalidBitWidth,
+    UnsupportedParameters,
+    UnsupportedActivation,
+    BudgetExceeded,
+}
+
+impl Display for CryptoError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CryptoError::MissingKey => write!(f, "Ciphertext doesn't have a server key to operate with"),
+            CryptoError::KeyMismatch => write!(f, "Ciphertexts were created with different server keys"),
+            CryptoError::ModulusMismatch => write!(f, "Ciphertexts have different message moduli"),
+            CryptoError::InsecureParameters => write!(f, "Parameter set isn't known to be secure"),
+            CryptoError::ParameterMismatch => write!(f, "Keys were generated with different parameters"),
+            CryptoError::InvalidKeyName => write!(f, "Key names may only contain letters, digits, '_' and '-'"),
+            CryptoError::SerializationError => write!(f, "Key cannot be serialized or the serialized data is invalid"),
+            CryptoError::IoError => write!(f, "Key cannot be read from or written to the file"),
+            CryptoError::InferenceError(err) => write!(f, "Neural network cannot be evaluated on the input: {}", err),
+            CryptoError::InvalidCiphertext => write!(f, "Ciphertext is empty or doesn't have a message modulus"),
+            CryptoError::InvalidBitWidth => write!(f, "Bit width must be between 1 and 64 bits and a multiple of the bits in a block"),
+            CryptoError::UnsupportedParameters => write!(f, "Carry buffer of the parameter set is too small for multi-block integers"),
+            CryptoError::UnsupportedActivation => write!(f, "Activation cannot be computed on this type"),
+            CryptoError::BudgetExceeded => write!(f, "Operation exceeds the noise budget even on freshly bootstrapped ciphertexts"),
+        }
+    }
+}
+
+// This is important for other errors to wrap this one.
+impl error::Error for CryptoError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        // Only the errors of the neural network keep their cause.
+        match self {
+            CryptoError::InferenceError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
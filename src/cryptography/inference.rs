@@ -0,0 +1,232 @@
+// Implements the split between the data owner and the model server for encrypted inference.
+//
+// The Client holds the client key. It encrypts the inputs, sends them to the server in an
+// InferenceRequest and decrypts the output it gets back in an InferenceResponse. The Server holds
+// the server key and the neural network. It never sees the client key, so it can't decrypt
+// anything it evaluates.
+//
+// The messages are serialized with bincode. Ciphertexts are serialized without the server key,
+// which the server attaches to them after receiving a request:
+//
+//     let (client, server_key) = Client::generate(&PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure)?;
+//     let mut server = Server::new(server_key, nnet);
+//
+//     let request = client.request(&input).to_bytes()?;                     // client -> server
+//     let response = server.handle(&InferenceRequest::from_bytes(&request)?)?.to_bytes()?;
+//     let output = client.decrypt_response(&InferenceResponse::from_bytes(&response)?);
+//
+// Inputs can also come from third parties, e.g. IoT devices, which shouldn't hold the client key.
+// They get an Encryptor with the client's public key instead, which can create requests but
+// can't decrypt anything.
+
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tfhe::shortint::prelude::*;
+use tfhe::shortint::CompressedPublicKey;
+use crate::cryptography::ciphtxt::CipherTextType;
+use crate::cryptography::errors::CryptoError;
+use crate::cryptography::key_gen::{custom_gen_keys, gen_compressed_public_key, PublicEncryptionKey, SecurityPolicy};
+use crate::neural_network::nnet::Nnet;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::parallel::{collect_indexed, MaybeSendSync};
+
+/// The encrypted input sent from the client to the server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceRequest {
+    pub input: Matrix<CipherTextType>,
+}
+
+/// The encrypted output sent from the server back to the client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InferenceResponse {
+    pub output: Matrix<CipherTextType>,
+}
+
+// Implements the wire format of the messages
+impl InferenceRequest {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CryptoError> {
+        bincode::serialize(self).map_err(|_| CryptoError::SerializationError)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<InferenceRequest, CryptoError> {
+        bincode::deserialize(bytes).map_err(|_| CryptoError::SerializationError)
+    }
+}
+
+// Implements the wire format of the messages
+impl InferenceResponse {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CryptoError> {
+        bincode::serialize(self).map_err(|_| CryptoError::SerializationError)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<InferenceResponse, CryptoError> {
+        bincode::deserialize(bytes).map_err(|_| CryptoError::SerializationError)
+    }
+}
+
+/// Returns a matrix with the same shape, strides and layout as mat, holding the data.
+fn with_data<T, R>(mat: &Matrix<T>, data: Vec<R>) -> Matrix<R> where T: Clone + Default, R: Clone + Default {
+    Matrix {
+        shape: mat.shape.clone(),
+        strides: mat.strides.clone(),
+        data,
+        layout: mat.layout,
+        size: mat.size,
+    }
+}
+
+/// The data owner. It holds the client key, which must never be sent to the server.
+pub struct Client {
+    client_key: ClientKey,
+}
+
+impl Client {
+    pub fn new(client_key: ClientKey) -> Client {
+        Client { client_key }
+    }
+
+    /// Generates a new key pair and returns the client together with the server key, which has to
+    /// be sent to the server.
+    pub fn generate(parameters_set: &Parameters, policy: SecurityPolicy) -> Result<(Client, ServerKey), CryptoError> {
+        let (client_key, server_key) = custom_gen_keys(parameters_set, policy)?;
+        Ok((Client::new(client_key), server_key))
+    }
+
+    /// Returns the message modulus. All of the computations on the server are done modulo it.
+    pub fn modulus(&self) -> u64 {
+        self.client_key.parameters.message_modulus.0 as u64
+    }
+
+    /// Encrypts every element of the matrix. The values are reduced modulo the message modulus,
+    /// so negative values become positive. The ciphertexts don't hold a server key, the server
+    /// attaches its own.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::inference::Client;
+    /// use Cryptonic::cryptography::key_gen::SecurityPolicy;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let (client, _server_key) = Client::generate(&PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure).unwrap();
+    ///
+    /// let input: Matrix<i64> = Matrix::from_iter(vec![2], vec![3, -1], Layout::RowMajor);
+    /// let encrypted = client.encrypt(&input);
+    /// assert_eq!(client.decrypt(&encrypted).data, vec![3, 3]); // -1 % 4
+    /// ```
+    pub fn encrypt(&self, input: &Matrix<i64>) -> Matrix<CipherTextType> {
+        let modulus = self.modulus();
+        let data = collect_indexed(input.data.len(), |i| {
+            let value = (input.data[i] as i128).rem_euclid(modulus as i128) as u64;
+            CipherTextType::new_without_key(self.client_key.encrypt(value), modulus)
+        });
+        with_data(input, data)
+    }
+
+    /// Generates a compressed public key, which data sources can encrypt inputs for this client
+    /// with. See Encryptor.
+    pub fn public_key(&self) -> CompressedPublicKey {
+        gen_compressed_public_key(&self.client_key)
+    }
+
+    /// Decrypts every element of the matrix. The values are in the range [0, modulus).
+    pub fn decrypt(&self, output: &Matrix<CipherTextType>) -> Matrix<i64> {
+        let data: Vec<i64> = output.data.iter().map(|value| value.decrypt(&self.client_key) as i64).collect();
+        with_data(output, data)
+    }
+
+    /// Encrypts the input and wraps it in a request for the server.
+    pub fn request(&self, input: &Matrix<i64>) -> InferenceRequest {
+        InferenceRequest { input: self.encrypt(input) }
+    }
+
+    /// Decrypts the output in a response from the server.
+    pub fn decrypt_response(&self, response: &InferenceResponse) -> Matrix<i64> {
+        self.decrypt(&response.output)
+    }
+}
+
+/// A data source which encrypts inputs for a client with its public key. It can't decrypt
+/// anything, not even the inputs it encrypted itself. Both kinds of public keys can be used, see
+/// PublicEncryptionKey.
+///
+/// # Examples
+/// ```no_run
+/// use tfhe::shortint::prelude::*;
+/// use Cryptonic::cryptography::inference::{Client, Encryptor};
+/// use Cryptonic::cryptography::key_gen::SecurityPolicy;
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// let (client, _server_key) = Client::generate(&PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure).unwrap();
+/// let encryptor = Encryptor::new(client.public_key());
+///
+/// let input: Matrix<i64> = Matrix::from_iter(vec![2], vec![3, -1], Layout::RowMajor);
+/// let encrypted = encryptor.encrypt(&input);
+/// assert_eq!(client.decrypt(&encrypted).data, vec![3, 3]); // -1 % 4
+/// ```
+pub struct Encryptor<K: PublicEncryptionKey = CompressedPublicKey> {
+    public_key: K,
+}
+
+impl<K: PublicEncryptionKey + MaybeSendSync> Encryptor<K> {
+    pub fn new(public_key: K) -> Encryptor<K> {
+        Encryptor { public_key }
+    }
+
+    /// Returns the message modulus. All of the computations on the server are done modulo it.
+    pub fn modulus(&self) -> u64 {
+        self.public_key.parameters().message_modulus.0 as u64
+    }
+
+    /// Encrypts every element of the matrix like Client::encrypt() does.
+    pub fn encrypt(&self, input: &Matrix<i64>) -> Matrix<CipherTextType> {
+        let modulus = self.modulus();
+        let data = collect_indexed(input.data.len(), |i| {
+            let value = (input.data[i] as i128).rem_euclid(modulus as i128) as u64;
+            CipherTextType::encrypt_with_public_key(value, &self.public_key)
+        });
+        with_data(input, data)
+    }
+
+    /// Encrypts the input and wraps it in a request for the server.
+    pub fn request(&self, input: &Matrix<i64>) -> InferenceRequest {
+        InferenceRequest { input: self.encrypt(input) }
+    }
+}
+
+/// The model server. It evaluates the neural network on encrypted inputs with the server key.
+pub struct Server {
+    server_key: Arc<ServerKey>,
+    nnet: Nnet<CipherTextType>,
+}
+
+impl Server {
+    pub fn new(server_key: ServerKey, nnet: Nnet<CipherTextType>) -> Server {
+        Server { server_key: Arc::new(server_key), nnet }
+    }
+
+    /// Returns the message modulus of the server key.
+    pub fn modulus(&self) -> u64 {
+        self.server_key.message_modulus.0 as u64
+    }
+
+    /// Attaches the server key to the encrypted input and runs it through the neural network.
+    /// Returns CryptoError::InvalidCiphertext if an element of the input is empty or has no
+    /// modulus, CryptoError::ModulusMismatch if the input was encrypted with other parameters and
+    /// CryptoError::InferenceError with the cause if the network can't evaluate it.
+    pub fn evaluate(&mut self, input: &Matrix<CipherTextType>) -> Result<Matrix<CipherTextType>, CryptoError> {
+        let mut input = input.clone();
+        for value in input.data.iter_mut() {
+            if value.is_def() || value.Modulus.is_none() {
+                return Err(CryptoError::InvalidCiphertext);
+            }
+            value.set_server_key(&self.server_key)?;
+        }
+        self.nnet.forward(input).map_err(|err| CryptoError::InferenceError(Box::new(err)))
+    }
+
+    /// Evaluates the input of the request and wraps the output in a response.
+    pub fn handle(&mut self, request: &InferenceRequest) -> Result<InferenceResponse, CryptoError> {
+        Ok(InferenceResponse { output: self.evaluate(&request.input)? })
+    }
+}
@@ -1,8 +1,9 @@
-// In this file I'll write the custom params for the mock examples in addition to which
-// I'll also implement ser/des functions whose results will be written directly to storage
-// and committed to allow faster build and test times.
+// In this file I'll write the custom params for the mock examples and the catalog of parameter
+// sets keys can be generated with. The ser/des functions for the keys are in key_store.rs.
 
 use tfhe::shortint::prelude::*;
+use tfhe::shortint::CompressedPublicKey;
+use crate::cryptography::errors::CryptoError;
 
 
 /// This is used for testing and development. Security isn't guaranteed.
@@ -25,19 +26,103 @@ pub const MY_PARAM: Parameters = Parameters {
     carry_modulus: CarryModulus(16),
 };
 
-/// This function takes a short_int::Parameter and num_blocks to return the keys relating to the
-/// type specified.
+/// The minimal security level in bits which key generation accepts without an explicit opt-in.
+pub const MIN_SECURITY_BITS: u32 = 128;
+
+/// A named parameter set together with what it supports. The message modulus is the number of
+/// values a ciphertext can hold, the carry modulus is how much room there is for carries before
+/// the server has to clean them with a bootstrap.
+#[derive(Debug, Clone, Copy)]
+pub struct ParameterPreset {
+    pub name: &'static str,
+    pub parameters: Parameters,
+    /// The estimated security level in bits. 0 means that the parameters are known to be
+    /// insecure.
+    pub security_bits: u32,
+}
+
+impl ParameterPreset {
+    pub fn message_modulus(&self) -> u64 {
+        self.parameters.message_modulus.0 as u64
+    }
+
+    pub fn carry_modulus(&self) -> u64 {
+        self.parameters.carry_modulus.0 as u64
+    }
+
+    pub fn is_secure(&self) -> bool {
+        self.security_bits >= MIN_SECURITY_BITS
+    }
+}
+
+/// The catalog of parameter sets which can be used for key generation. The tfhe presets target
+/// 128 bits of security, our dev preset is only meant for fast tests.
+pub const PRESETS: &[ParameterPreset] = &[
+    ParameterPreset { name: "message_1_carry_1", parameters: PARAM_MESSAGE_1_CARRY_1, security_bits: 128 },
+    ParameterPreset { name: "message_2_carry_2", parameters: PARAM_MESSAGE_2_CARRY_2, security_bits: 128 },
+    ParameterPreset { name: "message_2_carry_3", parameters: PARAM_MESSAGE_2_CARRY_3, security_bits: 128 },
+    ParameterPreset { name: "message_3_carry_3", parameters: PARAM_MESSAGE_3_CARRY_3, security_bits: 128 },
+    ParameterPreset { name: "message_4_carry_4", parameters: PARAM_MESSAGE_4_CARRY_4, security_bits: 128 },
+    ParameterPreset { name: "dev", parameters: MY_PARAM, security_bits: 0 },
+];
+
+/// Returns the preset with the given name.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::cryptography::key_gen::get_preset;
+///
+/// let preset = get_preset("message_2_carry_2").unwrap();
+/// assert_eq!(preset.message_modulus(), 4);
+/// assert!(preset.is_secure());
+/// assert!(!get_preset("dev").unwrap().is_secure());
+/// ```
+pub fn get_preset(name: &str) -> Option<&'static ParameterPreset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/// Returns the preset whose parameters are exactly the given ones.
+pub fn find_preset(parameters_set: &Parameters) -> Option<&'static ParameterPreset> {
+    PRESETS.iter().find(|preset| &preset.parameters == parameters_set)
+}
+
+/// Whether key generation may use parameter sets which aren't known to be secure.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecurityPolicy {
+    /// Only parameter sets from the catalog with at least MIN_SECURITY_BITS are accepted
+    RequireSecure,
+    /// Any parameter set is accepted. Only use this for tests and development.
+    AllowInsecure,
+}
+
+/// Checks that the parameter set may be used under the policy. Parameter sets which aren't in the
+/// catalog can't be checked, so they're treated like insecure ones.
+pub fn validate_parameters(parameters_set: &Parameters, policy: SecurityPolicy) -> Result<(), CryptoError> {
+    if policy == SecurityPolicy::AllowInsecure {
+        return Ok(());
+    }
+    match find_preset(parameters_set) {
+        Some(preset) if preset.is_secure() => Ok(()),
+        _ => Err(CryptoError::InsecureParameters),
+    }
+}
+
+/// This function takes a short_int::Parameter and returns the keys generated with it. The
+/// parameters are validated first, see validate_parameters().
 /// ```
 /// use tfhe::shortint::prelude::*;
-/// use Cryptonic::cryptography::key_gen::{custom_gen_keys, get_modulus, MY_PARAM};
+/// use Cryptonic::cryptography::errors::CryptoError;
+/// use Cryptonic::cryptography::key_gen::{custom_gen_keys, get_modulus, SecurityPolicy, MY_PARAM};
 ///
 /// let msg1 = 128;
 /// let msg2 = 128;
 ///
+/// // MY_PARAM is insecure, so it has to be explicitly allowed
+/// assert_eq!(custom_gen_keys(&MY_PARAM, SecurityPolicy::RequireSecure).unwrap_err(), CryptoError::InsecureParameters);
+///
 /// let modulus = get_modulus(&MY_PARAM);
-/// let (client_key, server_key) = custom_gen_keys(&MY_PARAM);
+/// let (client_key, server_key) = custom_gen_keys(&MY_PARAM, SecurityPolicy::AllowInsecure).unwrap();
 ///
-///     println!("{modulus}");
 ///    // We use the client key to encrypt two messages:
 ///    let ct_1 = client_key.encrypt(msg1);
 ///    let ct_2 = client_key.encrypt(msg2);
@@ -47,15 +132,68 @@ pub const MY_PARAM: Parameters = Parameters {
 ///
 ///    // We use the client key to decrypt the output of the circuit:
 ///    let output = client_key.decrypt(&ct_3);
-///    println!("{output}");
-///    println!("{}", (msg1 + msg2) % modulus);
 ///
 ///    assert_eq!(output, (msg1 + msg2) % modulus);
 /// ```
+pub fn custom_gen_keys(parameters_set: &Parameters, policy: SecurityPolicy) -> Result<(ClientKey, ServerKey), CryptoError> {
+    validate_parameters(parameters_set, policy)?;
+    Ok(gen_keys(*parameters_set))
+}
+
+/// Public keys can encrypt values which the server can operate on and the client can decrypt,
+/// but they can't decrypt anything, so they can be handed out to untrusted data sources.
+///
+/// There are two kinds of them. A PublicKey holds thousands of encryptions of zero, which take up
+/// hundreds of megabytes for the secure parameter sets. A CompressedPublicKey only stores their
+/// bodies and a seed to generate the rest from, so it's about a megabyte, but it has to generate
+/// them again on every encryption, which takes seconds instead of milliseconds.
+pub trait PublicEncryptionKey {
+    fn encrypt(&self, message: u64) -> Ciphertext;
+    fn parameters(&self) -> &Parameters;
+}
+
+impl PublicEncryptionKey for PublicKey {
+    fn encrypt(&self, message: u64) -> Ciphertext {
+        PublicKey::encrypt(self, message)
+    }
+
+    fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+}
+
+impl PublicEncryptionKey for CompressedPublicKey {
+    fn encrypt(&self, message: u64) -> Ciphertext {
+        CompressedPublicKey::encrypt(self, message)
+    }
+
+    fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+}
+
+/// Generates a public key from the client key. See PublicEncryptionKey.
 ///
+/// # Examples
+/// ```no_run
+/// use tfhe::shortint::prelude::*;
+/// use Cryptonic::cryptography::key_gen::{custom_gen_keys, gen_public_key, SecurityPolicy};
+///
+/// let (client_key, server_key) = custom_gen_keys(&PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure).unwrap();
+/// let public_key = gen_public_key(&client_key);
+///
+/// let ct_1 = public_key.encrypt(3);
+/// let ct_2 = public_key.encrypt(2);
+/// let ct_3 = server_key.unchecked_add(&ct_1, &ct_2);
+/// assert_eq!(client_key.decrypt(&ct_3), 1); // (3 + 2) % 4
+/// ```
+pub fn gen_public_key(client_key: &ClientKey) -> PublicKey {
+    PublicKey::new(client_key)
+}
 
-pub fn custom_gen_keys(parameters_set: &Parameters) -> (ClientKey, ServerKey) {
-    gen_keys(MY_PARAM)
+/// Generates a compressed public key from the client key. See PublicEncryptionKey.
+pub fn gen_compressed_public_key(client_key: &ClientKey) -> CompressedPublicKey {
+    CompressedPublicKey::new(client_key)
 }
 
 /// This function returns the modulus set by the parameter set and number of blocks.
@@ -64,7 +202,7 @@ pub fn custom_gen_keys(parameters_set: &Parameters) -> (ClientKey, ServerKey) {
 ///
 /// ```
 /// use tfhe::shortint::prelude::*;
-/// use Cryptonic::cryptography::key_gen::{custom_gen_keys, get_modulus, MY_PARAM};
+/// use Cryptonic::cryptography::key_gen::{get_modulus, MY_PARAM};
 ///
 /// println!("{}", get_modulus(&MY_PARAM)); // Prints 32768
 /// ```
@@ -0,0 +1,204 @@
+// Implements saving and loading of keys.
+//
+// Keys are stored in a versioned envelope, so that a key file can't be loaded as the wrong kind of
+// key, with a different version of the format or with parameters other than the ones it was
+// generated with. It consists of a header followed by the bincode encoded parameters and key:
+//
+//     magic       4 bytes     b"CRYK"
+//     version     u8          KEY_FORMAT_VERSION
+//     kind        u8          SerializableKey::KIND of the key type
+//     body        bincode encoded (Parameters, key)
+//
+// On top of that KeyStore manages a directory of named key pairs. Generating keys takes seconds
+// even for small parameter sets, so tests and tools should load them from a store instead.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tfhe::shortint::prelude::*;
+use tfhe::shortint::CompressedPublicKey;
+use crate::cryptography::errors::CryptoError;
+use crate::cryptography::key_gen::{custom_gen_keys, gen_compressed_public_key, SecurityPolicy};
+
+/// The first bytes of every key file
+pub const KEY_MAGIC: &[u8; 4] = b"CRYK";
+
+/// Version of the key file format. It gets incremented every time the format changes, so that old
+/// files are rejected instead of being misread.
+pub const KEY_FORMAT_VERSION: u8 = 1;
+
+/// Keys which can be stored in the envelope. The kind is written to the header, so that e.g. a
+/// server key file can't be loaded as a client key.
+pub trait SerializableKey: Serialize + DeserializeOwned {
+    const KIND: u8;
+}
+
+impl SerializableKey for ClientKey { const KIND: u8 = 0; }
+impl SerializableKey for ServerKey { const KIND: u8 = 1; }
+impl SerializableKey for PublicKey { const KIND: u8 = 2; }
+impl SerializableKey for CompressedPublicKey { const KIND: u8 = 3; }
+
+/// Encodes the key and the parameters it was generated with in the envelope described at the top
+/// of this file.
+pub fn key_to_bytes<K: SerializableKey>(key: &K, parameters: &Parameters) -> Result<Vec<u8>, CryptoError> {
+    let mut bytes: Vec<u8> = Vec::new();
+    bytes.extend_from_slice(KEY_MAGIC);
+    bytes.push(KEY_FORMAT_VERSION);
+    bytes.push(K::KIND);
+    match bincode::serialize_into(&mut bytes, &(parameters, key)) {
+        Ok(_) => Ok(bytes),
+        Err(_) => Err(CryptoError::SerializationError),
+    }
+}
+
+/// Decodes a key encoded by key_to_bytes() and returns it together with its parameters. Returns
+/// CryptoError::SerializationError if the header doesn't match or the data is invalid.
+pub fn key_from_bytes<K: SerializableKey>(bytes: &[u8]) -> Result<(K, Parameters), CryptoError> {
+    if bytes.len() < 6 || &bytes[..4] != KEY_MAGIC || bytes[4] != KEY_FORMAT_VERSION || bytes[5] != K::KIND {
+        return Err(CryptoError::SerializationError);
+    }
+    match bincode::deserialize::<(Parameters, K)>(&bytes[6..]) {
+        Ok((parameters, key)) => Ok((key, parameters)),
+        Err(_) => Err(CryptoError::SerializationError),
+    }
+}
+
+/// Writes the key to a file. The data is written to a temporary file first, so that an
+/// interrupted write can't leave a truncated key behind.
+pub fn save_key<K: SerializableKey, P: AsRef<Path>>(key: &K, parameters: &Parameters, path: P) -> Result<(), CryptoError> {
+    let bytes = key_to_bytes(key, parameters)?;
+    let tmp_path = path.as_ref().with_extension("tmp");
+    fs::write(&tmp_path, bytes).map_err(|_| CryptoError::IoError)?;
+    fs::rename(&tmp_path, path).map_err(|_| CryptoError::IoError)
+}
+
+/// Reads a key written by save_key().
+pub fn load_key<K: SerializableKey, P: AsRef<Path>>(path: P) -> Result<(K, Parameters), CryptoError> {
+    let bytes = fs::read(path).map_err(|_| CryptoError::IoError)?;
+    key_from_bytes(&bytes)
+}
+
+/// A directory of named key pairs. The client and the server key of a pair are stored in
+/// separate files, `<name>.client.key` and `<name>.server.key`, since they're usually needed by
+/// different parties. The public key of a pair is optional and stored in `<name>.public.key`. It's
+/// stored compressed, since uncompressed public keys take up hundreds of megabytes.
+///
+/// # Examples
+/// ```no_run
+/// use tfhe::shortint::prelude::*;
+/// use Cryptonic::cryptography::key_gen::SecurityPolicy;
+/// use Cryptonic::cryptography::key_store::KeyStore;
+///
+/// let store = KeyStore::new("keys").unwrap();
+/// // Only the first call generates the keys, all following ones load them
+/// let (client_key, server_key) = store
+///     .get_or_generate("default", &PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure)
+///     .unwrap();
+///
+/// // The server only needs its own key
+/// let server_key = store.load_server_key("default").unwrap();
+///
+/// // Data sources only need the public key to encrypt their inputs
+/// let public_key = store.get_or_generate_public_key("default").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct KeyStore {
+    dir: PathBuf,
+}
+
+impl KeyStore {
+    /// Opens the key store in the directory, creating the directory if it doesn't exist.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<KeyStore, CryptoError> {
+        fs::create_dir_all(&dir).map_err(|_| CryptoError::IoError)?;
+        Ok(KeyStore { dir: dir.as_ref().to_path_buf() })
+    }
+
+    /// Returns the directory of the store
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the path of a key file. Names may only contain ASCII letters, digits, '_' and '-',
+    /// so that they can't point outside of the directory.
+    fn key_path(&self, name: &str, kind: &str) -> Result<PathBuf, CryptoError> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+            return Err(CryptoError::InvalidKeyName);
+        }
+        Ok(self.dir.join(format!("{name}.{kind}.key")))
+    }
+
+    /// Returns true if both keys of the pair are in the store.
+    pub fn contains(&self, name: &str) -> bool {
+        match (self.key_path(name, "client"), self.key_path(name, "server")) {
+            (Ok(client_path), Ok(server_path)) => client_path.is_file() && server_path.is_file(),
+            _ => false,
+        }
+    }
+
+    /// Stores the key pair under the name, overwriting any keys with the same name.
+    pub fn save(&self, name: &str, client_key: &ClientKey, server_key: &ServerKey) -> Result<(), CryptoError> {
+        save_key(client_key, &client_key.parameters, self.key_path(name, "client")?)?;
+        save_key(server_key, &client_key.parameters, self.key_path(name, "server")?)
+    }
+
+    /// Loads the client key with the given name.
+    pub fn load_client_key(&self, name: &str) -> Result<ClientKey, CryptoError> {
+        let (client_key, _parameters) = load_key(self.key_path(name, "client")?)?;
+        Ok(client_key)
+    }
+
+    /// Loads the server key with the given name.
+    pub fn load_server_key(&self, name: &str) -> Result<ServerKey, CryptoError> {
+        let (server_key, _parameters) = load_key(self.key_path(name, "server")?)?;
+        Ok(server_key)
+    }
+
+    /// Stores the public key under the name of the key pair it was generated from.
+    pub fn save_public_key(&self, name: &str, public_key: &CompressedPublicKey) -> Result<(), CryptoError> {
+        save_key(public_key, &public_key.parameters, self.key_path(name, "public")?)
+    }
+
+    /// Loads the public key with the given name.
+    pub fn load_public_key(&self, name: &str) -> Result<CompressedPublicKey, CryptoError> {
+        let (public_key, _parameters) = load_key(self.key_path(name, "public")?)?;
+        Ok(public_key)
+    }
+
+    /// Loads the public key with the given name or generates it from the stored client key and
+    /// stores it if it's missing.
+    pub fn get_or_generate_public_key(&self, name: &str) -> Result<CompressedPublicKey, CryptoError> {
+        if self.key_path(name, "public")?.is_file() {
+            return self.load_public_key(name);
+        }
+        let public_key = gen_compressed_public_key(&self.load_client_key(name)?);
+        self.save_public_key(name, &public_key)?;
+        Ok(public_key)
+    }
+
+    /// Loads the key pair with the given name. Returns CryptoError::ParameterMismatch if the
+    /// keys were generated with different parameters.
+    pub fn load(&self, name: &str) -> Result<(ClientKey, ServerKey), CryptoError> {
+        let (client_key, client_parameters): (ClientKey, Parameters) = load_key(self.key_path(name, "client")?)?;
+        let (server_key, server_parameters): (ServerKey, Parameters) = load_key(self.key_path(name, "server")?)?;
+        if client_parameters != server_parameters {
+            return Err(CryptoError::ParameterMismatch);
+        }
+        Ok((client_key, server_key))
+    }
+
+    /// Loads the key pair with the given name or generates and stores it if it's missing. Returns
+    /// CryptoError::ParameterMismatch if the stored keys were generated with other parameters.
+    pub fn get_or_generate(&self, name: &str, parameters_set: &Parameters, policy: SecurityPolicy) -> Result<(ClientKey, ServerKey), CryptoError> {
+        if self.contains(name) {
+            let (client_key, server_key) = self.load(name)?;
+            if &client_key.parameters != parameters_set {
+                return Err(CryptoError::ParameterMismatch);
+            }
+            return Ok((client_key, server_key));
+        }
+        let (client_key, server_key) = custom_gen_keys(parameters_set, policy)?;
+        self.save(name, &client_key, &server_key)?;
+        Ok((client_key, server_key))
+    }
+}
@@ -1,3 +1,7 @@
 pub mod key_gen;
 pub mod ciphtxt;
 pub mod type_traits;
+pub mod errors;
+pub mod key_store;
+pub mod inference;
+pub mod radix;
@@ -0,0 +1,410 @@
+// Implements RadixCipherText, an encrypted integer which is wider than a single shortint block.
+//
+// A CipherTextType holds a single block, so all of its values are reduced modulo the message
+// modulus, which is only 4 for PARAM_MESSAGE_2_CARRY_2. A RadixCipherText splits the value into
+// digits in base message_modulus and encrypts each one of them in its own block, starting with
+// the least significant one. With num_bits bits the values are reduced modulo 2^num_bits instead,
+// so e.g. 16 bits take 8 blocks of 2 bits.
+//
+// Every block is kept clean, i.e. without anything in its carry buffer, between operations. An
+// operation works on the blocks one by one and then propagates the carries from the least to the
+// most significant block, where the final carry is dropped. This needs a carry buffer at least as
+// big as the message buffer, which is the case for all of the PARAM_MESSAGE_x_CARRY_y presets with
+// y >= x.
+//
+// Like CipherTextType it implements checked_*() methods and the std::ops traits on top of them,
+// it's serialized without the server key and a default value behaves like an encrypted zero.
+
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tfhe::shortint::prelude::*;
+use crate::cryptography::ciphtxt::{impl_ciphertext_op, impl_ciphertext_scalar_op, impl_scalar_ciphertext_op, unwrap_or_panic};
+use crate::cryptography::errors::CryptoError;
+use crate::tensor_library::parallel::collect_indexed;
+
+/// An encrypted integer of num_bits bits, stored in multiple shortint blocks.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RadixCipherText {
+    blocks: Vec<Ciphertext>,
+    #[serde(skip)]
+    server_key: Option<Arc<ServerKey>>,
+    // The message modulus of a single block, 0 for default values
+    block_modulus: u64,
+}
+
+/// Returns the number of blocks needed for num_bits bits. Returns CryptoError::InvalidBitWidth
+/// if the bits can't be split into whole blocks and CryptoError::UnsupportedParameters if the
+/// carry buffer can't hold the carries of the operations.
+fn calc_num_blocks(num_bits: usize, message_modulus: usize, carry_modulus: usize) -> Result<usize, CryptoError> {
+    if !message_modulus.is_power_of_two() || message_modulus < 2 || carry_modulus < message_modulus {
+        return Err(CryptoError::UnsupportedParameters);
+    }
+    let block_bits = message_modulus.trailing_zeros() as usize;
+    if num_bits == 0 || num_bits > 64 || !num_bits.is_multiple_of(block_bits) {
+        return Err(CryptoError::InvalidBitWidth);
+    }
+    Ok(num_bits / block_bits)
+}
+
+impl RadixCipherText {
+    /// Encrypts value as a num_bits bit integer. The value is reduced modulo 2^num_bits.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::sync::Arc;
+    /// use tfhe::shortint::prelude::*;
+    /// use Cryptonic::cryptography::radix::RadixCipherText;
+    /// let (client_key, server_key) = gen_keys(PARAM_MESSAGE_2_CARRY_2);
+    /// let server_key = Arc::new(server_key);
+    ///
+    /// // 4 blocks of 2 bits
+    /// let lhs = RadixCipherText::encrypt(200, 8, &client_key, &server_key).unwrap();
+    /// let rhs = RadixCipherText::encrypt(100, 8, &client_key, &server_key).unwrap();
+    ///
+    /// let result = lhs.checked_add(&rhs).unwrap();
+    /// assert_eq!(result.decrypt(&client_key).unwrap(), 44); // (200 + 100) % 256
+    /// ```
+    pub fn encrypt(value: u64, num_bits: usize, client_key: &ClientKey, server_key: &Arc<ServerKey>) -> Result<RadixCipherText, CryptoError> {
+        let parameters = client_key.parameters;
+        let num_blocks = calc_num_blocks(num_bits, parameters.message_modulus.0, parameters.carry_modulus.0)?;
+        let block_modulus = parameters.message_modulus.0 as u64;
+        let mut rest = value;
+        let mut blocks: Vec<Ciphertext> = Vec::with_capacity(num_blocks);
+        for _ in 0..num_blocks {
+            blocks.push(client_key.encrypt(rest % block_modulus));
+            rest /= block_modulus;
+        }
+        Ok(RadixCipherText { blocks, server_key: Some(server_key.clone()), block_modulus })
+    }
+
+    /// Decrypts the integer with the client key. The result is in the range [0, 2^num_bits). A
+    /// default value decrypts to 0.
+    pub fn decrypt(&self, client_key: &ClientKey) -> Result<u64, CryptoError> {
+        let mut result: u64 = 0;
+        for block in self.blocks.iter().rev() {
+            result = result.wrapping_mul(self.block_modulus) + client_key.decrypt(block);
+        }
+        Ok(result)
+    }
+
+    /// Decrypts the integer as a num_bits bit two's complement number, so that e.g. the result of
+    /// 1 - 2 decrypts to -1.
+    pub fn decrypt_signed(&self, client_key: &ClientKey) -> Result<i64, CryptoError> {
+        let value = self.decrypt(client_key)? as i128;
+        let modulus = self.modulus() as i128;
+        if value >= modulus / 2 {
+            Ok((value - modulus) as i64)
+        } else {
+            Ok(value as i64)
+        }
+    }
+
+    /// Returns the number of bits, or 0 for a default value.
+    pub fn num_bits(&self) -> usize {
+        match self.block_modulus {
+            0 => 0,
+            block_modulus => self.blocks.len() * block_modulus.trailing_zeros() as usize,
+        }
+    }
+
+    /// Returns the number of shortint blocks.
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns true if this is a default value, i.e. it doesn't hold any blocks.
+    pub fn is_def(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    /// Attaches the server key to the integer, e.g. after it was deserialized. Returns
+    /// CryptoError::ModulusMismatch if it was encrypted with another message modulus.
+    pub fn set_server_key(&mut self, server_key: &Arc<ServerKey>) -> Result<(), CryptoError> {
+        if !self.is_def() && self.block_modulus != server_key.message_modulus.0 as u64 {
+            return Err(CryptoError::ModulusMismatch);
+        }
+        self.server_key = Some(server_key.clone());
+        Ok(())
+    }
+
+    /// Returns 2^num_bits, the modulus all of the operations work with.
+    fn modulus(&self) -> u128 {
+        (self.block_modulus as u128).pow(self.blocks.len() as u32)
+    }
+
+    /// Returns the server key and a copy of the blocks, which the smart operations of the server
+    /// key can modify.
+    fn parts(&self) -> Result<(&ServerKey, Vec<Ciphertext>), CryptoError> {
+        match &self.server_key {
+            Some(server_key) if !self.is_def() => Ok((server_key, self.blocks.clone())),
+            _ => Err(CryptoError::MissingKey),
+        }
+    }
+
+    /// Checks that self and rhs can be operated on together, i.e. that they have the same number
+    /// of bits and were encrypted with the same keys.
+    fn check_compatible(&self, rhs: &RadixCipherText) -> Result<(), CryptoError> {
+        if self.block_modulus != rhs.block_modulus || self.blocks.len() != rhs.blocks.len() {
+            return Err(CryptoError::ModulusMismatch);
+        }
+        let same_key = match (&self.server_key, &rhs.server_key) {
+            (Some(lhs_key), Some(rhs_key)) => Arc::ptr_eq(lhs_key, rhs_key) || lhs_key == rhs_key,
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_key {
+            return Err(CryptoError::KeyMismatch);
+        }
+        Ok(())
+    }
+
+    /// Returns a RadixCipherText with the same key and modulus as self, holding the blocks.
+    fn with_blocks(&self, blocks: Vec<Ciphertext>) -> RadixCipherText {
+        RadixCipherText {
+            blocks,
+            server_key: self.server_key.clone(),
+            block_modulus: self.block_modulus,
+        }
+    }
+
+    /// Splits the scalar into one digit per block, after reducing it modulo 2^num_bits, so that
+    /// negative scalars become positive.
+    fn scalar_digits(&self, scalar: i64) -> Vec<u8> {
+        let mut rest = (scalar as i128).rem_euclid(self.modulus() as i128) as u128;
+        let mut digits: Vec<u8> = Vec::with_capacity(self.blocks.len());
+        for _ in 0..self.blocks.len() {
+            digits.push((rest % self.block_modulus as u128) as u8);
+            rest /= self.block_modulus as u128;
+        }
+        digits
+    }
+}
+
+/// Moves the carry of every block to the next one, so that all blocks are clean afterwards. The
+/// carry of the last block is dropped, which reduces the value modulo 2^num_bits. Blocks whose
+/// degree shows that they can't have a carry are skipped, since extracting it takes two bootstraps.
+fn propagate_carries(server_key: &ServerKey, blocks: &mut [Ciphertext]) {
+    for i in 0..blocks.len() {
+        if blocks[i].degree.0 < blocks[i].message_modulus.0 {
+            continue;
+        }
+        let mut carry = server_key.carry_extract(&blocks[i]);
+        blocks[i] = server_key.message_extract(&blocks[i]);
+        if i + 1 < blocks.len() {
+            server_key.smart_add_assign(&mut blocks[i + 1], &mut carry);
+        }
+    }
+}
+
+/// Adds rhs to lhs block by block and propagates the carries. Both have to be clean.
+fn add_blocks(server_key: &ServerKey, lhs: &mut [Ciphertext], rhs: &mut [Ciphertext]) {
+    for (lhs_block, rhs_block) in lhs.iter_mut().zip(rhs.iter_mut()) {
+        server_key.smart_add_assign(lhs_block, rhs_block);
+    }
+    propagate_carries(server_key, lhs);
+}
+
+// Implements the checked operations. All of them work modulo 2^num_bits.
+impl RadixCipherText {
+    /// Adds rhs to self.
+    pub fn checked_add(&self, rhs: &RadixCipherText) -> Result<RadixCipherText, CryptoError> {
+        if rhs.is_def() {
+            return Ok(self.clone());
+        }
+        if self.is_def() {
+            return Ok(rhs.clone());
+        }
+        self.check_compatible(rhs)?;
+        let (server_key, mut lhs_blocks) = self.parts()?;
+        let (_, mut rhs_blocks) = rhs.parts()?;
+        add_blocks(server_key, &mut lhs_blocks, &mut rhs_blocks);
+        Ok(self.with_blocks(lhs_blocks))
+    }
+
+    /// Subtracts rhs from self. It's computed as self + !rhs + 1, which is the same as
+    /// self - rhs modulo 2^num_bits.
+    pub fn checked_sub(&self, rhs: &RadixCipherText) -> Result<RadixCipherText, CryptoError> {
+        if rhs.is_def() {
+            return Ok(self.clone());
+        }
+        if self.is_def() {
+            return rhs.checked_neg();
+        }
+        self.check_compatible(rhs)?;
+        let (server_key, mut lhs_blocks) = self.parts()?;
+        let mut rhs_blocks = rhs.not_blocks()?;
+        for (lhs_block, rhs_block) in lhs_blocks.iter_mut().zip(rhs_blocks.iter_mut()) {
+            server_key.smart_add_assign(lhs_block, rhs_block);
+        }
+        server_key.smart_scalar_add_assign(&mut lhs_blocks[0], 1);
+        propagate_carries(server_key, &mut lhs_blocks);
+        Ok(self.with_blocks(lhs_blocks))
+    }
+
+    /// Multiplies self by rhs modulo 2^num_bits. The product of every pair of blocks is split
+    /// into its least and most significant digit, which are then added at their positions.
+    pub fn checked_mul(&self, rhs: &RadixCipherText) -> Result<RadixCipherText, CryptoError> {
+        if self.is_def() || rhs.is_def() {
+            return Ok(RadixCipherText::default());
+        }
+        self.check_compatible(rhs)?;
+        let (server_key, lhs_blocks) = self.parts()?;
+        let (_, rhs_blocks) = rhs.parts()?;
+        let num_blocks = lhs_blocks.len();
+
+        // Only the pairs which end up below 2^num_bits are multiplied
+        let pairs: Vec<(usize, usize)> = (0..num_blocks)
+            .flat_map(|i| (0..num_blocks - i).map(move |j| (i, j)))
+            .collect();
+        let products: Vec<(Ciphertext, Ciphertext)> = collect_indexed(pairs.len(), |k| {
+            let (i, j) = pairs[k];
+            let (mut lhs_block, mut rhs_block) = (lhs_blocks[i].clone(), rhs_blocks[j].clone());
+            let lsb = server_key.smart_mul_lsb(&mut lhs_block, &mut rhs_block);
+            let msb = server_key.smart_mul_msb(&mut lhs_block, &mut rhs_block);
+            (lsb, msb)
+        });
+
+        let mut result: Vec<Ciphertext> = (0..num_blocks).map(|_| server_key.create_trivial(0)).collect();
+        for i in 0..num_blocks {
+            let mut lsb_row: Vec<Ciphertext> = (0..num_blocks).map(|_| server_key.create_trivial(0)).collect();
+            let mut msb_row: Vec<Ciphertext> = (0..num_blocks).map(|_| server_key.create_trivial(0)).collect();
+            for ((lhs_idx, rhs_idx), (lsb, msb)) in pairs.iter().zip(products.iter()) {
+                if *lhs_idx != i {
+                    continue;
+                }
+                lsb_row[i + rhs_idx] = lsb.clone();
+                if i + rhs_idx + 1 < num_blocks {
+                    msb_row[i + rhs_idx + 1] = msb.clone();
+                }
+            }
+            add_blocks(server_key, &mut result, &mut lsb_row);
+            add_blocks(server_key, &mut result, &mut msb_row);
+        }
+        Ok(self.with_blocks(result))
+    }
+
+    /// Returns the additive inverse of self, computed as !self + 1.
+    pub fn checked_neg(&self) -> Result<RadixCipherText, CryptoError> {
+        if self.is_def() {
+            return Ok(RadixCipherText::default());
+        }
+        let (server_key, _) = self.parts()?;
+        let mut blocks = self.not_blocks()?;
+        server_key.smart_scalar_add_assign(&mut blocks[0], 1);
+        propagate_carries(server_key, &mut blocks);
+        Ok(self.with_blocks(blocks))
+    }
+
+    /// Adds the plaintext scalar to self. Negative scalars are added modulo 2^num_bits. A default
+    /// value has no key to encrypt the scalar with, so it returns CryptoError::MissingKey.
+    pub fn checked_scalar_add(&self, scalar: i64) -> Result<RadixCipherText, CryptoError> {
+        let (server_key, mut blocks) = self.parts()?;
+        for (block, digit) in blocks.iter_mut().zip(self.scalar_digits(scalar)) {
+            if digit != 0 {
+                server_key.smart_scalar_add_assign(block, digit);
+            }
+        }
+        propagate_carries(server_key, &mut blocks);
+        Ok(self.with_blocks(blocks))
+    }
+
+    /// Subtracts the plaintext scalar from self. A default value has no key to encrypt the scalar
+    /// with, so it returns CryptoError::MissingKey.
+    pub fn checked_scalar_sub(&self, scalar: i64) -> Result<RadixCipherText, CryptoError> {
+        // -i64::MIN overflows, but it wraps to i64::MIN, which is the same modulo 2^num_bits
+        self.checked_scalar_add(scalar.wrapping_neg())
+    }
+
+    /// Multiplies self by the plaintext scalar. Negative scalars are reduced modulo 2^num_bits,
+    /// so multiplying by -1 negates the value. Self is multiplied by every digit of the scalar,
+    /// and the results are shifted to the position of the digit and added.
+    pub fn checked_scalar_mul(&self, scalar: i64) -> Result<RadixCipherText, CryptoError> {
+        if self.is_def() {
+            return Ok(RadixCipherText::default());
+        }
+        let (server_key, blocks) = self.parts()?;
+        let num_blocks = blocks.len();
+        let mut result: Vec<Ciphertext> = (0..num_blocks).map(|_| server_key.create_trivial(0)).collect();
+        for (shift, digit) in self.scalar_digits(scalar).into_iter().enumerate() {
+            if digit == 0 {
+                continue;
+            }
+            let mut row: Vec<Ciphertext> = (0..num_blocks).map(|_| server_key.create_trivial(0)).collect();
+            // The blocks are clean, so the product of a block and a digit always fits in the carry
+            // buffer. smart_scalar_mul() would overestimate its degree and throw the carry away.
+            for i in 0..num_blocks - shift {
+                row[i + shift] = server_key.unchecked_scalar_mul(&blocks[i], digit);
+            }
+            add_blocks(server_key, &mut result, &mut row);
+        }
+        Ok(self.with_blocks(result))
+    }
+
+    /// Returns max(self, 0), reading self as a two's complement number. The sign is the most
+    /// significant bit of the last block, so a mask which is all ones for non-negative values
+    /// and zero for negative ones is computed from it and anded with every block.
+    pub fn checked_relu(&self) -> Result<RadixCipherText, CryptoError> {
+        if self.is_def() {
+            return Ok(self.clone());
+        }
+        let (server_key, blocks) = self.parts()?;
+        let (half, max_digit) = (self.block_modulus / 2, self.block_modulus - 1);
+        let accumulator = server_key.generate_accumulator(|x| if x % self.block_modulus >= half { 0 } else { max_digit });
+        let mask = server_key.keyswitch_programmable_bootstrap(&blocks[blocks.len() - 1], &accumulator);
+        let blocks = collect_indexed(blocks.len(), |i| server_key.smart_bitand(&mut blocks[i].clone(), &mut mask.clone()));
+        Ok(self.with_blocks(blocks))
+    }
+
+    /// Returns the blocks of !self, i.e. every digit d replaced by block_modulus - 1 - d.
+    fn not_blocks(&self) -> Result<Vec<Ciphertext>, CryptoError> {
+        let (server_key, blocks) = self.parts()?;
+        let max_digit = self.block_modulus - 1;
+        let accumulator = server_key.generate_accumulator(|x| max_digit - x % self.block_modulus);
+        Ok(blocks.iter().map(|block| server_key.keyswitch_programmable_bootstrap(block, &accumulator)).collect())
+    }
+}
+
+/// Ciphertexts can't be printed, so only the number of bits is shown.
+impl Debug for RadixCipherText {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.is_def() {
+            false => write!(f, "RadixCipherText({} bits)", self.num_bits()),
+            true => write!(f, "RadixCipherText(default)"),
+        }
+    }
+}
+
+impl_ciphertext_op!(RadixCipherText, Add, add, AddAssign, add_assign, checked_add);
+impl_ciphertext_op!(RadixCipherText, Sub, sub, SubAssign, sub_assign, checked_sub);
+impl_ciphertext_op!(RadixCipherText, Mul, mul, MulAssign, mul_assign, checked_mul);
+
+impl_ciphertext_scalar_op!(RadixCipherText, u8, Add, add, AddAssign, add_assign, checked_scalar_add);
+impl_ciphertext_scalar_op!(RadixCipherText, u8, Sub, sub, SubAssign, sub_assign, checked_scalar_sub);
+impl_ciphertext_scalar_op!(RadixCipherText, u8, Mul, mul, MulAssign, mul_assign, checked_scalar_mul);
+impl_ciphertext_scalar_op!(RadixCipherText, i32, Add, add, AddAssign, add_assign, checked_scalar_add);
+impl_ciphertext_scalar_op!(RadixCipherText, i32, Sub, sub, SubAssign, sub_assign, checked_scalar_sub);
+impl_ciphertext_scalar_op!(RadixCipherText, i32, Mul, mul, MulAssign, mul_assign, checked_scalar_mul);
+
+impl_scalar_ciphertext_op!(RadixCipherText, u8, Add, add, checked_scalar_add);
+impl_scalar_ciphertext_op!(RadixCipherText, u8, Mul, mul, checked_scalar_mul);
+impl_scalar_ciphertext_op!(RadixCipherText, i32, Add, add, checked_scalar_add);
+impl_scalar_ciphertext_op!(RadixCipherText, i32, Mul, mul, checked_scalar_mul);
+
+impl Neg for RadixCipherText {
+    type Output = RadixCipherText;
+
+    fn neg(self) -> RadixCipherText {
+        unwrap_or_panic(self.checked_neg())
+    }
+}
+
+impl Neg for &RadixCipherText {
+    type Output = RadixCipherText;
+
+    fn neg(self) -> RadixCipherText {
+        unwrap_or_panic(self.checked_neg())
+    }
+}
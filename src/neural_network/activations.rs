@@ -1,7 +1,152 @@
+// Implements the activation functions of the neural networks.
+//
+// Comparisons can't be computed on ciphertexts, so the activations on encrypted values are lookup
+// tables evaluated with programmable bootstrapping. The messages of the ciphertexts are integers
+// modulo the message modulus, which are read as two's complement numbers, i.e. the upper half of
+// [0, modulus) holds the negative values. With a modulus of 4, 0 and 1 are positive, while 2 and
+// 3 are -2 and -1.
+//
+// The Activate trait is implemented both for the plaintext integers and for the encrypted types,
+// so that the same network gives the same results (modulo the message modulus) for all of them.
+
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+use crate::cryptography::ciphtxt::CipherTextType;
+use crate::cryptography::errors::CryptoError;
+use crate::cryptography::radix::RadixCipherText;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::parallel::{collect_indexed, MaybeSendSync};
+
 // We assume T::default() is the 0 of the type T
 pub fn relu_usize<T>(_in: T) -> T where T: PartialOrd + Default{
     if _in < T::default() {
         return T::default();
     }
     _in
-}
\ No newline at end of file
+}
+
+/// Activation functions which can be computed on both plaintext and encrypted values.
+#[derive(Clone)]
+pub enum Activation {
+    /// max(x, 0)
+    Relu,
+    /// -1, 0 or 1 depending on the sign of x
+    Sign,
+    /// min(max(x, 0), max)
+    ClippedRelu(u64),
+    /// A user-supplied lookup table. On ciphertexts it gets the message in [0, modulus), while
+    /// plaintext values are passed as their two's complement u64, so the table should use
+    /// wrapping arithmetic to give the same results for both.
+    Custom(Arc<dyn Fn(u64) -> u64 + Send + Sync>),
+}
+
+impl Activation {
+    /// Wraps the function in Activation::Custom.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::neural_network::activations::{Activate, Activation};
+    /// let square = Activation::custom(|x| x * x);
+    /// assert_eq!(3i32.activate(&square), Ok(9));
+    /// ```
+    pub fn custom<F>(func: F) -> Activation where F: Fn(u64) -> u64 + Send + Sync + 'static {
+        Activation::Custom(Arc::new(func))
+    }
+}
+
+/// The lookup tables can't be printed, so only the name of the activation is shown.
+impl Debug for Activation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Activation::Relu => write!(f, "Relu"),
+            Activation::Sign => write!(f, "Sign"),
+            Activation::ClippedRelu(max) => write!(f, "ClippedRelu({max})"),
+            Activation::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+/// Types on which the activation functions can be computed.
+pub trait Activate: Sized {
+    fn activate(&self, activation: &Activation) -> Result<Self, CryptoError>;
+}
+
+// Implements the activations for the plaintext integers
+macro_rules! impl_activate_plain {
+    ($ty:ty) => {
+        impl Activate for $ty {
+            fn activate(&self, activation: &Activation) -> Result<$ty, CryptoError> {
+                Ok(match activation {
+                    Activation::Relu => relu_usize(*self),
+                    Activation::Sign => self.signum(),
+                    Activation::ClippedRelu(max) => relu_usize(*self).min(<$ty>::try_from(*max).unwrap_or(<$ty>::MAX)),
+                    Activation::Custom(func) => func(*self as i64 as u64) as $ty,
+                })
+            }
+        }
+    };
+}
+
+impl_activate_plain!(i32);
+impl_activate_plain!(i64);
+
+impl Activate for CipherTextType {
+    /// Evaluates the activation with a programmable bootstrap. A default value is an encrypted
+    /// zero without a key, and all of the activations except Custom map 0 to 0, so it stays the
+    /// same. Custom returns CryptoError::MissingKey for it.
+    fn activate(&self, activation: &Activation) -> Result<CipherTextType, CryptoError> {
+        if self.is_def() && !matches!(activation, Activation::Custom(_)) {
+            return Ok(CipherTextType::default());
+        }
+        let modulus = self.Modulus.ok_or(CryptoError::MissingKey)?;
+        let is_negative = move |x: u64| x >= modulus / 2;
+        match activation {
+            Activation::Relu => self.apply_lookup_table(|x| if is_negative(x) { 0 } else { x }),
+            Activation::Sign => self.apply_lookup_table(|x| match x {
+                0 => 0,
+                x if is_negative(x) => modulus - 1,
+                _ => 1,
+            }),
+            Activation::ClippedRelu(max) => self.apply_lookup_table(|x| if is_negative(x) { 0 } else { x.min(*max) }),
+            Activation::Custom(func) => self.apply_lookup_table(|x| func(x)),
+        }
+    }
+}
+
+impl Activate for RadixCipherText {
+    /// Lookup tables can only be evaluated on a single block, so only Relu, which just needs the
+    /// sign, is supported. The other activations return CryptoError::UnsupportedActivation.
+    fn activate(&self, activation: &Activation) -> Result<RadixCipherText, CryptoError> {
+        match activation {
+            Activation::Relu => self.checked_relu(),
+            _ => Err(CryptoError::UnsupportedActivation),
+        }
+    }
+}
+
+/// Computes the activation on every element of the matrix.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::neural_network::activations::{activate, Activation};
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// let mat: Matrix<i32> = Matrix::from_iter(vec![4], vec![-2, -1, 1, 7], Layout::RowMajor);
+///
+/// assert_eq!(activate(&mat, &Activation::Relu).unwrap().data, vec![0, 0, 1, 7]);
+/// assert_eq!(activate(&mat, &Activation::ClippedRelu(3)).unwrap().data, vec![0, 0, 1, 3]);
+/// assert_eq!(activate(&mat, &Activation::Sign).unwrap().data, vec![-1, -1, 1, 1]);
+/// ```
+pub fn activate<T>(mat: &Matrix<T>, activation: &Activation) -> Result<Matrix<T>, CryptoError> where T: Clone + Default + MaybeSendSync + Activate {
+    let data: Result<Vec<T>, CryptoError> = collect_indexed(mat.data.len(), |i| mat.data[i].activate(activation))
+        .into_iter()
+        .collect();
+    Ok(Matrix {
+        shape: mat.shape.clone(),
+        strides: mat.strides.clone(),
+        data: data?,
+        layout: mat.layout,
+        size: mat.size,
+    })
+}
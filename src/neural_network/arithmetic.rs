@@ -0,0 +1,75 @@
+// Implements the arithmetic the layers and merge nodes compute with. The std::ops traits can't
+// return a Result, so the ciphertexts panic in them when their keys or moduli don't fit together.
+// The layers use the CheckedArithmetic trait instead, which returns the CryptoError, so that a
+// network evaluated on bad inputs fails with an NnetError instead of bringing down the process.
+//
+// The plaintext integers wrap on overflow, the same way the ciphertexts work modulo their message
+// modulus.
+
+use crate::cryptography::ciphtxt::CipherTextType;
+use crate::cryptography::errors::CryptoError;
+use crate::cryptography::radix::RadixCipherText;
+
+/// Types the layers and merge nodes can compute with. The weights and biases are plaintext i32s,
+/// so they're used as scalars.
+pub trait CheckedArithmetic: Sized {
+    fn try_add(&self, rhs: &Self) -> Result<Self, CryptoError>;
+
+    fn try_mul(&self, rhs: &Self) -> Result<Self, CryptoError>;
+
+    fn try_scalar_add(&self, scalar: i32) -> Result<Self, CryptoError>;
+
+    fn try_scalar_mul(&self, scalar: i32) -> Result<Self, CryptoError>;
+}
+
+// Implements the arithmetic for the plaintext integers
+macro_rules! impl_checked_arithmetic_plain {
+    ($ty:ty) => {
+        impl CheckedArithmetic for $ty {
+            fn try_add(&self, rhs: &$ty) -> Result<$ty, CryptoError> {
+                Ok(self.wrapping_add(*rhs))
+            }
+
+            fn try_mul(&self, rhs: &$ty) -> Result<$ty, CryptoError> {
+                Ok(self.wrapping_mul(*rhs))
+            }
+
+            fn try_scalar_add(&self, scalar: i32) -> Result<$ty, CryptoError> {
+                Ok(self.wrapping_add(scalar as $ty))
+            }
+
+            fn try_scalar_mul(&self, scalar: i32) -> Result<$ty, CryptoError> {
+                Ok(self.wrapping_mul(scalar as $ty))
+            }
+        }
+    };
+}
+
+impl_checked_arithmetic_plain!(i32);
+impl_checked_arithmetic_plain!(i64);
+
+// Implements the arithmetic for the encrypted types with their checked operations
+macro_rules! impl_checked_arithmetic_encrypted {
+    ($ty:ty) => {
+        impl CheckedArithmetic for $ty {
+            fn try_add(&self, rhs: &$ty) -> Result<$ty, CryptoError> {
+                self.checked_add(rhs)
+            }
+
+            fn try_mul(&self, rhs: &$ty) -> Result<$ty, CryptoError> {
+                self.checked_mul(rhs)
+            }
+
+            fn try_scalar_add(&self, scalar: i32) -> Result<$ty, CryptoError> {
+                self.checked_scalar_add(scalar as i64)
+            }
+
+            fn try_scalar_mul(&self, scalar: i32) -> Result<$ty, CryptoError> {
+                self.checked_scalar_mul(scalar as i64)
+            }
+        }
+    };
+}
+
+impl_checked_arithmetic_encrypted!(CipherTextType);
+impl_checked_arithmetic_encrypted!(RadixCipherText);
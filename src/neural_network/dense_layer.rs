@@ -1,18 +1,69 @@
+// Implements the fully connected layer. It computes activation(W·x + b), where x is the input
+// vector, W is a matrix with one row per output and one column per input and b holds one bias per
+// output.
+//
+// The weights and biases are plaintext i32s, since they belong to the model owner and only the
+// inputs are encrypted. The elements of the input can be plaintext integers as well as ciphertexts,
+// which get multiplied with the plaintext weights. W·x + b is computed with try_matmul() and
+// try_zip_with() on top of CheckedArithmetic, so that errors of the ciphertexts are returned
+// instead of panicking.
+
 use std::marker::PhantomData;
+use crate::neural_network::activations::{activate, Activate, Activation};
+use crate::neural_network::arithmetic::CheckedArithmetic;
+use crate::neural_network::errors::NnetError;
 use crate::neural_network::layer_trait::Layer;
-use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::matrix::{try_matmul, Matrix};
+use crate::tensor_library::ops::try_zip_with;
+use crate::tensor_library::parallel::MaybeSendSync;
 
 pub struct DenseLayer<T> {
+    // Used in the errors about the layer
+    name : String,
     input_shape : Vec<usize>,
     output_shape : Vec<usize>,
+    // Shape [outputs, inputs]
+    weights : Matrix<i32>,
+    // Shape [outputs]
+    biases : Matrix<i32>,
+    activation : Option<Activation>,
     _phantom : PhantomData<T>
 }
 
-impl<T> Layer for DenseLayer<T> {
+impl<T> Layer for DenseLayer<T> where T: Clone + Default + MaybeSendSync + CheckedArithmetic + Activate {
     type CType = T;
 
-    fn forward(&mut self, input: Matrix<Self::CType>) -> Matrix<Self::CType> where <Self as Layer>::CType: Clone + Default {
-        input
+    /// Computes activation(W·x + b). The input has to be a vector with one element per column of
+    /// the weights, or a batch of such vectors with shape [n, inputs], which results in an output
+    /// with shape [n, outputs].
+    ///
+    /// A Nnet checks the inputs of its layers against get_input_shape(), so batches can only be
+    /// passed to the layer directly.
+    fn forward(&mut self, input: Matrix<Self::CType>) -> Result<Matrix<Self::CType>, NnetError> where <Self as Layer>::CType: Clone + Default {
+        let shape = input.shape();
+        if shape.len() > 2 || shape[shape.len() - 1] != self.input_shape[0] {
+            return Err(NnetError::ShapeMismatch {
+                layer: self.name.clone(),
+                expected: self.input_shape.clone(),
+                found: shape.clone(),
+            });
+        }
+
+        // x·Wᵀ computes W·x for every row of a batch. The transpose only swaps the strides.
+        let mut weights = self.weights.clone();
+        weights.transpose();
+        let output = try_matmul(
+            &input,
+            &weights,
+            |item, weight| item.try_scalar_mul(*weight).map_err(NnetError::from),
+            |sum, product| sum.try_add(&product).map_err(NnetError::from),
+        )?;
+        // The biases get broadcast over the rows of a batch
+        let output = try_zip_with(&output, &self.biases, |item, bias| item.try_scalar_add(bias).map_err(NnetError::from))?;
+        match &self.activation {
+            Some(activation) => Ok(activate(&output, activation)?),
+            None => Ok(output),
+        }
     }
 
     fn get_input_shape(&self) -> &Vec<usize> {
@@ -25,17 +76,53 @@ impl<T> Layer for DenseLayer<T> {
 }
 
 impl<T> DenseLayer<T> {
-    pub fn new(input_shape : Option<Vec<usize>>, output_shape : Option<Vec<usize>>) -> DenseLayer<T> {
-        DenseLayer {
-            input_shape : match input_shape {
-                Some(shape) => shape,
-                None => Vec::new()
-            },
-            output_shape : match output_shape {
-                Some(shape) => shape,
-                None => Vec::new()
-            },
-            _phantom : PhantomData::default(),
+    /// Creates a layer from its weights, which have one row per output and one column per input,
+    /// and its biases, which have one element per output. The name is used in the errors about the
+    /// layer. Returns NnetError::WeightCountMismatch if the shapes don't fit together.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::neural_network::activations::Activation;
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_trait::Layer;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let weights = Matrix::from_iter(vec![2, 3], vec![1, 0, 2, -1, 1, 0], Layout::RowMajor);
+    /// let biases = Matrix::from_iter(vec![2], vec![1, -4], Layout::RowMajor);
+    /// let mut layer: DenseLayer<i32> = DenseLayer::new("hidden", weights, biases, Some(Activation::Relu)).unwrap();
+    ///
+    /// let input = Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor);
+    /// // relu([1 + 6 + 1, -1 + 2 - 4])
+    /// assert_eq!(layer.forward(input).unwrap().data, vec![8, 0]);
+    /// ```
+    pub fn new(name : &str, weights : Matrix<i32>, biases : Matrix<i32>, activation : Option<Activation>) -> Result<DenseLayer<T>, NnetError> {
+        if weights.shape().len() != 2 || biases.shape() != &vec![weights.shape()[0]] {
+            return Err(NnetError::WeightCountMismatch { layer: name.to_string() });
         }
+        Ok(DenseLayer {
+            name : name.to_string(),
+            input_shape : vec![weights.shape()[1]],
+            output_shape : vec![weights.shape()[0]],
+            weights,
+            biases,
+            activation,
+            _phantom : PhantomData,
+        })
     }
-}
\ No newline at end of file
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn weights(&self) -> &Matrix<i32> {
+        &self.weights
+    }
+
+    pub fn biases(&self) -> &Matrix<i32> {
+        &self.biases
+    }
+
+    pub fn activation(&self) -> Option<&Activation> {
+        self.activation.as_ref()
+    }
+}
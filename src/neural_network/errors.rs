@@ -0,0 +1,97 @@
+use std::error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::cryptography::errors::CryptoError;
+use crate::tensor_library::errors::MatrixError;
+
+// Unlike the other errors, most of these keep the layer they're about, since a network can have
+// many layers of the same kind. Layers of an Nnet are named by their id, the ones of a Sequential
+// model by their name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NnetError {
+    UnknownLayer(usize),
+    InvalidLink,
+    DuplicateLink,
+    ShapeMismatch { layer: String, expected: Vec<usize>, found: Vec<usize> },
+    MissingInputLink { layer: String },
+    MissingOutputLink { layer: String },
+    TooManyInputLinks { layer: String },
+    MissingNetworkInput,
+    MissingNetworkOutput,
+    InputCountMismatch { expected: usize, found: usize },
+    OutputCountMismatch { expected: usize, found: usize },
+    Cycle,
+    WeightCountMismatch { layer: String },
+    EmptyModel,
+    MissingLayer,
+    MissingInputSize,
+    MissingWeights { layer: String },
+    MatrixError(MatrixError),
+    CryptoError(CryptoError),
+}
+
+impl Display for NnetError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            NnetError::UnknownLayer(id) => write!(f, "Layer {} doesn't exist in the network", id),
+            NnetError::InvalidLink => write!(f, "Both ends of the link are None, expected at least one of them to be a layer"),
+            NnetError::DuplicateLink => write!(f, "The link already exists, only merge nodes can be linked to the same node more than once"),
+            NnetError::ShapeMismatch { layer, expected, found } => write!(
+                f,
+                "Layer {} expects an input of shape {:?}, but gets {:?}", layer, expected, found
+            ),
+            NnetError::MissingInputLink { layer } => write!(f, "Layer {} doesn't have a link to it", layer),
+            NnetError::MissingOutputLink { layer } => write!(
+                f,
+                "Layer {} doesn't have a link from it to another layer or to None", layer
+            ),
+            NnetError::TooManyInputLinks { layer } => write!(
+                f,
+                "Layer {} has more than one link to it, add a merge node instead", layer
+            ),
+            NnetError::MissingNetworkInput => write!(f, "The network must have a link from None to its first layer"),
+            NnetError::MissingNetworkOutput => write!(f, "The network must have a link from its last layer to None"),
+            NnetError::InputCountMismatch { expected, found } => write!(
+                f,
+                "The network has {} inputs, but got {}", expected, found
+            ),
+            NnetError::OutputCountMismatch { expected, found } => write!(
+                f,
+                "Expected {} outputs, but the network has {}, use forward_multiple() instead", expected, found
+            ),
+            NnetError::Cycle => write!(f, "The links between the layers form a cycle"),
+            NnetError::WeightCountMismatch { layer } => write!(
+                f,
+                "The weights or biases of layer {} don't match its number of inputs and outputs", layer
+            ),
+            NnetError::EmptyModel => write!(f, "The model must have at least one layer"),
+            NnetError::MissingLayer => write!(f, "A layer must be added before its weights, biases, activation or name are set"),
+            NnetError::MissingInputSize => write!(f, "The number of inputs of the model must be set with input()"),
+            NnetError::MissingWeights { layer } => write!(f, "Layer {} doesn't have weights or biases", layer),
+            NnetError::MatrixError(err) => write!(f, "{}", err),
+            NnetError::CryptoError(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl error::Error for NnetError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            NnetError::MatrixError(err) => Some(err),
+            NnetError::CryptoError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<MatrixError> for NnetError {
+    fn from(err: MatrixError) -> Self {
+        NnetError::MatrixError(err)
+    }
+}
+
+impl From<CryptoError> for NnetError {
+    fn from(err: CryptoError) -> Self {
+        NnetError::CryptoError(err)
+    }
+}
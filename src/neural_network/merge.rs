@@ -0,0 +1,59 @@
+// Implements the merge nodes of the network. Unlike a layer, which gets exactly one input, a merge
+// node combines the outputs of all the nodes linked to it into one matrix, which makes skip
+// connections (e.g. residual blocks) and models with several branches possible. The inputs are
+// combined in the order in which their links were added, which matters for concatenation.
+
+use std::fmt::Debug;
+use crate::neural_network::arithmetic::CheckedArithmetic;
+use crate::neural_network::errors::NnetError;
+use crate::tensor_library::errors::MatrixError;
+use crate::tensor_library::matrix::{concat, Matrix};
+use crate::tensor_library::ops::try_zip_with;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Merge {
+    /// Adds the inputs element-wise. They must have the same shape.
+    Add,
+    /// Multiplies the inputs element-wise. They must have the same shape.
+    Multiply,
+    /// Concatenates the inputs along the given axis. All other dimensions must be the same.
+    Concat(usize),
+}
+
+impl Merge {
+    /// Combines the inputs into one matrix. Returns MatrixError::DimError if their shapes don't fit
+    /// together and NnetError::CryptoError if the elements can't be added or multiplied.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::neural_network::merge::Merge;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let inputs = vec![
+    ///     Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor),
+    ///     Matrix::from_iter(vec![2], vec![3, 4], Layout::RowMajor),
+    /// ];
+    ///
+    /// assert_eq!(Merge::Add.merge(inputs.clone()).unwrap().data, vec![4, 6]);
+    /// assert_eq!(Merge::Multiply.merge(inputs.clone()).unwrap().data, vec![3, 8]);
+    /// assert_eq!(Merge::Concat(0).merge(inputs).unwrap().data, vec![1, 2, 3, 4]);
+    /// ```
+    pub fn merge<T>(&self, inputs: Vec<Matrix<T>>) -> Result<Matrix<T>, NnetError> where T: Clone + Default + Debug + CheckedArithmetic {
+        let mut inputs = inputs.into_iter();
+        let mut result = inputs.next().ok_or(NnetError::MatrixError(MatrixError::InvalidParams))?;
+        for input in inputs {
+            result = match self {
+                Merge::Add | Merge::Multiply if result.shape() != input.shape() => {
+                    return Err(NnetError::MatrixError(MatrixError::DimError));
+                },
+                Merge::Add => try_zip_with(&result, &input, |lhs, rhs| lhs.try_add(&rhs).map_err(NnetError::from))?,
+                Merge::Multiply => try_zip_with(&result, &input, |lhs, rhs| lhs.try_mul(&rhs).map_err(NnetError::from))?,
+                Merge::Concat(axis) if *axis >= result.shape().len() => {
+                    return Err(NnetError::MatrixError(MatrixError::DimError));
+                },
+                Merge::Concat(axis) => concat(result, input, *axis)?.0,
+            };
+        }
+        Ok(result)
+    }
+}
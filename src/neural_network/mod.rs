@@ -4,3 +4,7 @@ pub mod layer_type;
 pub mod test_layer;
 pub mod dense_layer;
 pub mod activations;
+pub mod arithmetic;
+pub mod merge;
+pub mod sequential;
+pub mod errors;
@@ -1,218 +1,430 @@
-// use std::async_iter::from_iter;
-use std::collections::{HashMap, VecDeque};
+// Implements the neural network as a graph of layers and merge nodes, which share the same ids.
+// The links between them are directed edges, where None stands for the network itself: a link
+// from None to a layer feeds one of the inputs of the network to it and a link from a layer to None
+// makes its output one of the outputs of the network. The inputs and the outputs are numbered in
+// the order their links were added. A merge node may have several links from the same node, e.g.
+// to compute x * x, every other link may only be added once.
+//
+// Nodes can be given a name, which the errors use instead of the id, so that it's clear which
+// node is wrong in a bigger model.
+//
+// Before running the network the graph is validated, i.e. it has to be acyclic, every layer has
+// to get exactly one input, every merge node at least one and the output of every node has to be
+// used. The nodes are then executed in topological order, where nodes which could run in any order
+// run by increasing id, so the same network always executes the same way.
+
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
-use std::ops::{Add, AddAssign, Mul, MulAssign};
-// This import was deprecated
-// use crate::cryptography::type_traits::{MyAdd, MyMul};
+use crate::neural_network::activations::Activate;
+use crate::neural_network::arithmetic::CheckedArithmetic;
+use crate::neural_network::errors::NnetError;
 use crate::neural_network::layer_trait::Layer;
 use crate::neural_network::layer_type::LayerType;
-use crate::tensor_library::layout::Layout;
-use crate::tensor_library::layout::Layout::RowMajor;
-use crate::tensor_library::matrix::{Matrix, MatrixIter, multiply_1d, multiply_2d, multiply_scalar, multiply_scalar_generic};
-
-// TODO: Add tests and examples for everything
-pub struct Link(Option<usize>, Option<usize>);
-
-pub struct Nnet<T> where T : Clone + Default + AddAssign + MulAssign  + Add<i32, Output = T>{
-    // HashMap <id, (&Layer, biases)>
-    layers : HashMap<usize, (LayerType<T>, Vec<i32>)>,
-    // HashMap <(from_layer, to_layer), weights>
-    links : HashMap<(Option<usize>, Option<usize>), Vec<i32>>,
+use crate::neural_network::merge::Merge;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::parallel::MaybeSendSync;
+
+pub struct Link(pub Option<usize>, pub Option<usize>);
+
+pub struct Nnet<T> where T : Clone + Default {
+    // BTreeMap <id, Layer>
+    layers : BTreeMap<usize, LayerType<T>>,
+    // BTreeMap <id, Merge>
+    merges : BTreeMap<usize, Merge>,
+    // (from_layer, to_layer) in the order they were added, None is the input or the output of the
+    // network
+    links : Vec<(Option<usize>, Option<usize>)>,
+    // BTreeMap <id, name>, nodes without a name are called by their id
+    names : BTreeMap<usize, String>,
+}
+
+impl<T> Default for Nnet<T> where T : Clone + Default + Debug + MaybeSendSync + CheckedArithmetic + Activate {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<T> Nnet<T> where T : Clone + Default + Debug + AddAssign + MulAssign + Add<i32, Output = T> + Mul<i32> + Mul<i32, Output = T>, i32: Mul<T>{
+impl<T> Nnet<T> where T : Clone + Default + Debug + MaybeSendSync + CheckedArithmetic + Activate {
     pub fn new() -> Nnet<T> {
         Nnet {
-            layers: HashMap::new(),
-            links: HashMap::new(),
+            layers: BTreeMap::new(),
+            merges: BTreeMap::new(),
+            links: Vec::new(),
+            names: BTreeMap::new(),
         }
     }
 
-    /// Adds new layer to the neural network
+    /// Adds new layer to the neural network. The layer holds its own weights and biases.
     ///
     /// # Example:
     /// ```
-    /// use Cryptonic::layers::dense_layer;
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
     /// use Cryptonic::neural_network::nnet::Nnet;
-    /// let mut nnet : Nnet<dense_layer> = Nnet::new();
-    /// let l : dense_layer = dense_layer {};
-    /// //println!("{}", nnet.add_layer(&l, vec![1;5]));
-    /// //println!("{}", nnet.add_layer(&l, vec![1;5]));
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let weights = Matrix::from_iter(vec![2, 2], vec![1, 2, 3, 4], Layout::RowMajor);
+    /// let biases = Matrix::from_iter(vec![2], vec![0, 1], Layout::RowMajor);
+    ///
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let id = nnet.add_layer(LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap()));
+    /// assert_eq!(id, 0);
     /// ```
     ///
-    pub fn add_layer(&mut self, layer_type : LayerType<T>, biases : Vec<i32>) -> usize {
-        self.layers.insert(self.layers.len(), (layer_type, biases));
+    pub fn add_layer(&mut self, layer_type : LayerType<T>) -> usize {
+        let id = self.next_id();
+        self.layers.insert(id, layer_type);
 
         // The id of the added layer is returned so it can be used when attaching links to it
-        self.layers.len()-1
+        id
     }
 
-    /// Adds link between two layers if they exists
+    /// Adds a merge node to the neural network, which combines the outputs of all the nodes
+    /// linked to it. The inputs are combined in the order in which their links were added.
+    ///
+    /// # Example:
+    /// ```
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
+    /// use Cryptonic::neural_network::merge::Merge;
+    /// use Cryptonic::neural_network::nnet::Nnet;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let weights = Matrix::from_iter(vec![2, 2], vec![0, 1, 1, 0], Layout::RowMajor);
+    /// let biases = Matrix::from_iter(vec![2], vec![0, 0], Layout::RowMajor);
+    ///
+    /// // Residual block, which computes layer(x) + x
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let layer = nnet.add_layer(LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap()));
+    /// let merge = nnet.add_merge(Merge::Add);
+    /// nnet.add_link(None, Some(layer)).unwrap();
+    /// nnet.add_link(None, Some(merge)).unwrap();
+    /// nnet.add_link(Some(layer), Some(merge)).unwrap();
+    /// nnet.add_link(Some(merge), None).unwrap();
+    ///
+    /// // The network has two inputs, so the input is passed to both
+    /// let input = Matrix::from_iter(vec![2], vec![1, 10], Layout::RowMajor);
+    /// let result = nnet.forward_multiple(vec![input.clone(), input]).unwrap();
+    /// assert_eq!(result[0].data, vec![11, 11]);
+    /// ```
+    pub fn add_merge(&mut self, merge : Merge) -> usize {
+        let id = self.next_id();
+        self.merges.insert(id, merge);
+        id
+    }
+
+    /// Names a layer or merge node. The name is used in the errors about it instead of its id.
+    ///
+    /// # Example:
+    /// ```
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::errors::NnetError;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
+    /// use Cryptonic::neural_network::nnet::Nnet;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let weights = Matrix::from_iter(vec![1, 2], vec![1, 1], Layout::RowMajor);
+    /// let biases = Matrix::from_iter(vec![1], vec![0], Layout::RowMajor);
+    ///
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let id = nnet.add_layer(LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap()));
+    /// nnet.set_name(id, "sum").unwrap();
+    /// nnet.add_link(None, Some(id)).unwrap();
+    /// assert_eq!(nnet.topological_order().err(), Some(NnetError::MissingNetworkOutput));
+    /// nnet.add_link(Some(id), None).unwrap();
+    ///
+    /// let result = nnet.forward(Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor));
+    /// let expected = NnetError::ShapeMismatch { layer: "sum".to_string(), expected: vec![2], found: vec![3] };
+    /// assert_eq!(result.err(), Some(expected));
+    /// ```
+    pub fn set_name(&mut self, node_id : usize, name : &str) -> Result<(), NnetError> {
+        if !self.contains_node(node_id) {
+            return Err(NnetError::UnknownLayer(node_id));
+        }
+        self.names.insert(node_id, name.to_string());
+        Ok(())
+    }
+
+    /// Returns the name of a layer or merge node, which is its id unless it was named with
+    /// set_name().
+    pub fn get_name(&self, node_id : usize) -> String {
+        node_name(&self.names, node_id)
+    }
+
+    /// Adds link between two layers or merge nodes if they exists. None as the first layer is the
+    /// input of the network and None as the second one is its output. The shapes are checked only
+    /// between two layers, since the shape of a merge node depends on its inputs. Returns
+    /// NnetError::DuplicateLink if the link already exists, unless it goes to a merge node.
     ///
     /// # Example:
     ///```
-    /// use Cryptonic::layers::dense_layer;
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
     /// use Cryptonic::neural_network::nnet::Nnet;
-    /// use Cryptonic::nnet::Nnet;
-    /// let mut nnet : Nnet<dense_layer> = Nnet::new();
-    /// let l : dense_layer = dense_layer {};
-    /// //println!("{}", nnet.add_layer(&l, vec![1;5]));
-    /// //println!("{}", nnet.add_layer(&l, vec![1;5]));
-    /// //nnet.add_link(0, 1);
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let weights = Matrix::from_iter(vec![2, 2], vec![1, 2, 3, 4], Layout::RowMajor);
+    /// let biases = Matrix::from_iter(vec![2], vec![0, 1], Layout::RowMajor);
+    ///
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let id = nnet.add_layer(LayerType::DenseLayer(DenseLayer::new("sum", weights, biases, None).unwrap()));
+    /// nnet.add_link(None, Some(id)).unwrap();
+    /// nnet.add_link(Some(id), None).unwrap();
+    ///
+    /// let result = nnet.forward(Matrix::from_iter(vec![2], vec![1, 1], Layout::RowMajor)).unwrap();
+    /// assert_eq!(result.data, vec![3, 8]);
     /// ```
     ///
-    pub fn add_link(&mut self, from_layer_id : Option<usize>, to_layer_id : Option<usize>, weights : Vec<i32>) -> Result<(), &str> {
-        if from_layer_id.is_none() && !to_layer_id.is_none() {
-            return self.add_first_link(to_layer_id.unwrap(), weights);
+    pub fn add_link(&mut self, from_layer_id : Option<usize>, to_layer_id : Option<usize>) -> Result<(), NnetError> {
+        let (from_layer_id, to_layer_id) = match (from_layer_id, to_layer_id) {
+            (None, Some(to_layer_id)) => return self.add_first_link(to_layer_id),
+            (Some(from_layer_id), None) => return self.add_last_link(from_layer_id),
+            (None, None) => return Err(NnetError::InvalidLink),
+            (Some(from_layer_id), Some(to_layer_id)) => (from_layer_id, to_layer_id),
+        };
+        for layer_id in [from_layer_id, to_layer_id] {
+            if !self.contains_node(layer_id) {
+                return Err(NnetError::UnknownLayer(layer_id));
+            }
+        }
+
+        if let (Some(from_layer), Some(to_layer)) = (self.layers.get(&from_layer_id), self.layers.get(&to_layer_id)) {
+            if from_layer.get_output_shape() != to_layer.get_input_shape() {
+                return Err(NnetError::ShapeMismatch {
+                    layer: self.get_name(to_layer_id),
+                    expected: to_layer.get_input_shape().clone(),
+                    found: from_layer.get_output_shape().clone(),
+                });
+            }
         }
-        if !from_layer_id.is_none() && to_layer_id.is_none() {
-            return self.add_last_link(from_layer_id.unwrap(), weights);
+
+        self.insert_link(Some(from_layer_id), Some(to_layer_id))
+    }
+
+    /// Runs the input through the network. This is for networks with a single input and a single
+    /// output, see forward_multiple() for the others.
+    pub fn forward(&mut self, input: Matrix<T>) -> Result<Matrix<T>, NnetError>{
+        let (input_count, output_count) = (self.get_input_layer_ids().len(), self.get_output_layer_ids().len());
+        if input_count != 1 {
+            return Err(NnetError::InputCountMismatch { expected: input_count, found: 1 });
         }
-        if from_layer_id.is_none() && to_layer_id.is_none() {
-            return Err("Both layers are None, expected at least one of them to be Some<usize>");
+        if output_count != 1 {
+            return Err(NnetError::OutputCountMismatch { expected: 1, found: output_count });
         }
-        let from_layer = self.get_layer_by_id(from_layer_id.unwrap());
-        let to_layer = self.get_layer_by_id(to_layer_id.unwrap());
-        if to_layer.is_none() || from_layer.is_none() {
-            return Err("One of the layer ids you provide doesn't exist in the  current neural network");
+        let mut outputs = self.forward_multiple(vec![input])?;
+        Ok(outputs.remove(0))
+    }
+
+    /// Runs the inputs through the network and returns its outputs. The inputs and the outputs
+    /// are in the order in which their links were added.
+    ///
+    /// # Example:
+    /// ```
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
+    /// use Cryptonic::neural_network::nnet::Nnet;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let dense_layer = |weights: Vec<i32>| {
+    ///     let weights = Matrix::from_iter(vec![1, 2], weights, Layout::RowMajor);
+    ///     let biases = Matrix::from_iter(vec![1], vec![0], Layout::RowMajor);
+    ///     LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap())
+    /// };
+    ///
+    /// // Two separate layers, which get their own input
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let sum = nnet.add_layer(dense_layer(vec![1, 1]));
+    /// let difference = nnet.add_layer(dense_layer(vec![1, -1]));
+    /// nnet.add_link(None, Some(difference)).unwrap();
+    /// nnet.add_link(None, Some(sum)).unwrap();
+    /// nnet.add_link(Some(sum), None).unwrap();
+    /// nnet.add_link(Some(difference), None).unwrap();
+    ///
+    /// let inputs = vec![
+    ///     Matrix::from_iter(vec![2], vec![5, 3], Layout::RowMajor),
+    ///     Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor),
+    /// ];
+    /// let outputs = nnet.forward_multiple(inputs).unwrap();
+    /// assert_eq!(outputs[0].data, vec![3]); // 1 + 2
+    /// assert_eq!(outputs[1].data, vec![2]); // 5 - 3
+    /// ```
+    pub fn forward_multiple(&mut self, inputs: Vec<Matrix<T>>) -> Result<Vec<Matrix<T>>, NnetError>{
+        let order = self.topological_order()?;
+        let input_ids = self.get_input_layer_ids();
+        if inputs.len() != input_ids.len() {
+            return Err(NnetError::InputCountMismatch { expected: input_ids.len(), found: inputs.len() });
         }
-        let from_layer = from_layer.unwrap();
-        let to_layer = to_layer.unwrap();
 
-        if from_layer.get_output_shape() != to_layer.get_input_shape() {
-            return Err("Incompatible dimensions! The output shape of the first layer is different from the input shape of the second!")
+        // Every input of the network is moved to the link it belongs to, so that a merge node
+        // linked to the input twice gets two of them
+        let mut inputs = inputs.into_iter();
+        let mut link_inputs: Vec<Option<Matrix<T>>> = self.links.iter()
+            .map(|(from, _to)| if from.is_none() { inputs.next() } else { None })
+            .collect();
+        let mut outputs: BTreeMap<usize, Matrix<T>> = BTreeMap::new();
+        for node_id in order {
+            let mut node_inputs: Vec<Matrix<T>> = Vec::new();
+            for (link, (from, _to)) in self.links.iter().enumerate().filter(|(_link, (_from, to))| to == &Some(node_id)) {
+                let node_input = match from {
+                    Some(from) => outputs.get(from).cloned(),
+                    None => link_inputs[link].take(),
+                };
+                node_inputs.push(node_input.ok_or_else(|| NnetError::MissingInputLink { layer: self.get_name(node_id) })?);
+            }
+
+            let output = match (self.layers.get_mut(&node_id), self.merges.get(&node_id)) {
+                (Some(layer), _) => {
+                    // The layers are borrowed mutably, so the name is looked up in the field
+                    let input = node_inputs.pop().ok_or_else(|| NnetError::MissingInputLink { layer: node_name(&self.names, node_id) })?;
+                    if input.shape() != layer.get_input_shape() {
+                        return Err(NnetError::ShapeMismatch {
+                            layer: node_name(&self.names, node_id),
+                            expected: layer.get_input_shape().clone(),
+                            found: input.shape().clone(),
+                        });
+                    }
+                    layer.forward(input)?
+                },
+                (None, Some(merge)) => merge.merge(node_inputs)?,
+                (None, None) => return Err(NnetError::UnknownLayer(node_id)),
+            };
+            outputs.insert(node_id, output);
         }
 
-        self.links.insert((from_layer_id, to_layer_id), weights);
-        Ok(())
+        self.get_output_layer_ids().iter()
+            .map(|node_id| outputs.get(node_id).cloned().ok_or(NnetError::UnknownLayer(*node_id)))
+            .collect()
     }
 
-    pub fn forward(&mut self, input: Matrix<T>) -> Result<(Matrix<T>), &str>{
-        // let first_layer_id = match &self.get_first_layer_id() {
-        //     Some(a) => a,
-        //     None => return Err("You must select the first layer by adding a link from None to first layer id!")
-        // };
-        let (first_layer, ibiases) = self.layers.get_mut(&0).unwrap();
-        if input.shape() != first_layer.get_input_shape() {
-            return Err("Invalid input shape!");
+    /// Returns the ids of the layers the inputs of the network are linked to, in the order of the
+    /// inputs.
+    pub fn get_input_layer_ids(&self) -> Vec<usize> {
+        self.links.iter().filter_map(|(from, to)| if from.is_none() { *to } else { None }).collect()
+    }
+
+    /// Returns the ids of the layers whose outputs are the outputs of the network, in the order of
+    /// the outputs.
+    pub fn get_output_layer_ids(&self) -> Vec<usize> {
+        self.links.iter().filter_map(|(from, to)| if to.is_none() { *from } else { None }).collect()
+    }
+
+    /// Validates the graph of the network and returns the ids of its layers and merge nodes in the
+    /// order they're executed in. Returns an error if a layer doesn't get exactly one input, if a
+    /// merge node doesn't get any, if the output of a node isn't used or if the links form a cycle.
+    ///
+    /// # Example:
+    /// ```
+    /// use Cryptonic::neural_network::dense_layer::DenseLayer;
+    /// use Cryptonic::neural_network::layer_type::LayerType;
+    /// use Cryptonic::neural_network::nnet::Nnet;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let dense_layer = || {
+    ///     let weights = Matrix::from_iter(vec![2, 2], vec![1, 0, 0, 1], Layout::RowMajor);
+    ///     let biases = Matrix::from_iter(vec![2], vec![0, 0], Layout::RowMajor);
+    ///     LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap())
+    /// };
+    ///
+    /// let mut nnet: Nnet<i32> = Nnet::new();
+    /// let (first, second, third) = (nnet.add_layer(dense_layer()), nnet.add_layer(dense_layer()), nnet.add_layer(dense_layer()));
+    /// nnet.add_link(None, Some(third)).unwrap();
+    /// nnet.add_link(Some(third), Some(first)).unwrap();
+    /// nnet.add_link(Some(first), Some(second)).unwrap();
+    /// nnet.add_link(Some(second), None).unwrap();
+    /// assert_eq!(nnet.topological_order().unwrap(), vec![third, first, second]);
+    ///
+    /// // Linking the last layer back to the first one creates a cycle
+    /// nnet.add_link(Some(second), Some(third)).unwrap();
+    /// assert!(nnet.topological_order().is_err());
+    /// ```
+    pub fn topological_order(&self) -> Result<Vec<usize>, NnetError> {
+        if self.get_input_layer_ids().is_empty() {
+            return Err(NnetError::MissingNetworkInput);
         }
-        let mut links_left : VecDeque<(&Option<usize>, &Option<usize>, &Vec<i32>)> = VecDeque::new();
-        for ((from, to), weights) in &self.links {
-            if !from.is_none() && &from.unwrap() == &0 {
-                links_left.push_back((from, to, weights));
-            }
+        if self.get_output_layer_ids().is_empty() {
+            return Err(NnetError::MissingNetworkOutput);
         }
-        let input_iterator : MatrixIter<T> = MatrixIter {
-            mat: &input,
-            index: vec![0; input.shape().len()],
-            current_el: None,
-            empty: false,
-        };
-        let mut current_input:Vec<T> = Vec::new();
-        for (el, idx) in input_iterator {
-            current_input.push(el+ibiases[idx.iter().fold(0, |acc, &x| acc + x)]);
-        }
-        let mut current_input = Matrix::from_iter(vec![current_input.len()], current_input, Layout::RowMajor);
-
-        while !links_left.is_empty() {
-            let (current_from_id, current_to_id, weights)  = links_left.pop_back().unwrap();
-            let (current_from_layer, _biases) = self.layers.get_mut(&current_from_id.unwrap()).unwrap();
-            if current_to_id.is_none() {
-                current_input = current_from_layer.forward(current_input);
-                break;
+
+        let node_ids: Vec<usize> = self.layers.keys().chain(self.merges.keys()).copied().collect();
+        for node_id in &node_ids {
+            let link_count = self.links.iter().filter(|(_from, to)| to == &Some(*node_id)).count();
+            match (link_count, self.layers.contains_key(node_id)) {
+                (0, _) => return Err(NnetError::MissingInputLink { layer: self.get_name(*node_id) }),
+                (1, _) | (_, false) => {},
+                _ => return Err(NnetError::TooManyInputLinks { layer: self.get_name(*node_id) }),
             }
-            let (current_to_layer, biases) = self.layers.get_mut(&current_to_id.unwrap()).unwrap();
-            // Add next links
-            for ((from, to), weights) in &self.links {
-                if !from.is_none() && &from.unwrap() == &current_to_id.unwrap() {
-                    links_left.push_back((from, to, weights));
-                }
+            if !self.links.iter().any(|(from, _to)| from == &Some(*node_id)) {
+                return Err(NnetError::MissingOutputLink { layer: self.get_name(*node_id) });
             }
+        }
 
-            // Forward propagation (could be in separate function)
-
-            let mut result : Vec<T> = Vec::new();
-            let input_iterator : MatrixIter<T> = MatrixIter {
-                mat: &current_input,
-                index: vec![0; current_input.shape().len()],
-                current_el: None,
-                empty: false,
-            };
-            let mut ctr: usize = 0;
-            for (el, idx) in input_iterator {
-                let weights: Vec<i32> = weights.clone()[ctr*current_input.clone().shape.iter().sum::<usize>()..(ctr+1)*current_input.clone().shape.iter().sum::<usize>()].to_vec();
-                let weights_matrix : Matrix<i32> = Matrix::from_iter(vec![current_input.clone().shape.iter().sum()], weights, Layout::RowMajor);
-                let mut current_output_el = multiply_scalar_generic(weights_matrix.clone(), el.clone());
-
-                let iterator : MatrixIter<T> = MatrixIter {
-                    mat: &current_output_el,
-                    index: vec![0; current_output_el.shape().len()],
-                    current_el: None,
-                    empty: false,
-                };
-                // let mut sum = T::default();
-                for (el, idx) in iterator {
-                    if result.len() > idx.iter().fold(0, |acc, &x| acc + x) {
-                        result[idx.iter().fold(0, |acc, &x| acc + x)] += el;
-                    }
-                    else {
-                        result.push(el);
-                    }
+        // Kahn's algorithm, where only the links between nodes count, since the inputs of the
+        // network are always available. Every link counts, so a merge node linked twice to the
+        // same node is decremented twice. The nodes which are ready to run are kept sorted, so that
+        // the one with the smallest id runs first.
+        let mut in_degrees: BTreeMap<usize, usize> = node_ids.iter().map(|id| (*id, 0)).collect();
+        let mut next_nodes: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (from, to) in &self.links {
+            if let (Some(from), Some(to)) = (from, to) {
+                *in_degrees.entry(*to).or_default() += 1;
+                next_nodes.entry(*from).or_default().push(*to);
+            }
+        }
+        let mut ready: BTreeSet<usize> = in_degrees.iter().filter(|(_id, in_degree)| **in_degree == 0).map(|(id, _in_degree)| *id).collect();
+        let mut order: Vec<usize> = Vec::with_capacity(node_ids.len());
+        while let Some(node_id) = ready.pop_first() {
+            order.push(node_id);
+            for next_node_id in next_nodes.get(&node_id).into_iter().flatten() {
+                let in_degree = in_degrees.entry(*next_node_id).or_default();
+                *in_degree -= 1;
+                if *in_degree == 0 {
+                    ready.insert(*next_node_id);
                 }
-                // sum = sum + *biases.get(idx[0]).unwrap();
-
-                ctr+=1;
             }
-            result = result.iter().zip(biases.iter()).map(|(a, b)| a.clone()+b.clone()).collect();
-
-            // Activation function (and other layer operations)
-            let forwarded_input: Matrix<T> = Matrix::from_iter(vec![result.len()], result, Layout::RowMajor);
-            current_input = current_to_layer.forward(forwarded_input);
         }
+        if order.len() != node_ids.len() {
+            return Err(NnetError::Cycle);
+        }
+        Ok(order)
+    }
 
-        Ok((current_input))
+    // Layers and merge nodes share the same ids, which are given in the order they're added in
+    fn next_id(&self) -> usize {
+        self.layers.len() + self.merges.len()
     }
 
-    fn add_first_link(&mut self, layer_id : usize, weights : Vec<i32>)  -> Result<(), &str> {
-        if !self.layers.iter().any(|(x, _)| x == &layer_id) {
-            return Err("The layer id you provide doesn't exist in the  current neural network");
-        }
-        self.links.insert((None, Some(layer_id)), weights);
-        Ok(())
+    fn contains_node(&self, node_id : usize) -> bool {
+        self.layers.contains_key(&node_id) || self.merges.contains_key(&node_id)
     }
 
-    fn add_last_link(&mut self, layer_id : usize, weights : Vec<i32>)  -> Result<(), &str> {
-        if !self.layers.iter().any(|(x, _)| x == &layer_id) {
-            return Err("The layer id you provide doesn't exist in the  current neural network");
+    /// Adds the link. Only merge nodes may get the same link more than once, since they combine
+    /// all their inputs.
+    fn insert_link(&mut self, from_layer_id : Option<usize>, to_layer_id : Option<usize>) -> Result<(), NnetError> {
+        let to_merge = to_layer_id.is_some_and(|id| self.merges.contains_key(&id));
+        if !to_merge && self.links.contains(&(from_layer_id, to_layer_id)) {
+            return Err(NnetError::DuplicateLink);
         }
-        self.links.insert((Some(layer_id), None), weights);
+        self.links.push((from_layer_id, to_layer_id));
         Ok(())
     }
 
-    fn get_first_layer_id(&self) ->  &Option<usize> {
-        for ((from, to), _weights) in &self.links {
-            if from.is_none() {
-                return to;
-            }
+    fn add_first_link(&mut self, layer_id : usize)  -> Result<(), NnetError> {
+        if !self.contains_node(layer_id) {
+            return Err(NnetError::UnknownLayer(layer_id));
         }
-        &None
+        self.insert_link(None, Some(layer_id))
     }
 
-    fn get_last_layer_id(&self) ->  &Option<usize> {
-        for ((from, to), _weights) in &self.links {
-            if to.is_none() {
-                return from;
-            }
+    fn add_last_link(&mut self, layer_id : usize)  -> Result<(), NnetError> {
+        if !self.contains_node(layer_id) {
+            return Err(NnetError::UnknownLayer(layer_id));
         }
-        &None
+        self.insert_link(Some(layer_id), None)
     }
 
-    fn get_layer_by_id(&self, id:usize) -> Option<&LayerType<T>> {
-        for (key, value) in &self.layers {
-            if key == &id {
-                return Some(&value.0);
-            }
-        }
-        None
-    }
+}
+
+// Returns the name of the node, or its id if it doesn't have one
+fn node_name(names : &BTreeMap<usize, String>, node_id : usize) -> String {
+    names.get(&node_id).cloned().unwrap_or_else(|| node_id.to_string())
 }
@@ -0,0 +1,184 @@
+// Implements a builder for networks whose layers run one after the other, which is what most
+// models look like. It hides the ids and links of Nnet: every layer is linked to the previous one,
+// the first one to the input and the last one to the output. Only the number of inputs of the
+// model is given, every layer gets as many inputs as the previous one has outputs.
+//
+// Every layer has a name, which is used in the errors, so that it's clear which layer is wrong in
+// a bigger model. The names are kept in the network, so its errors use them as well. By default
+// the layers are called dense_0, dense_1, ... in the order they're added in. The weights are only
+// checked in build(), after all names are known.
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use crate::neural_network::activations::{Activate, Activation};
+use crate::neural_network::arithmetic::CheckedArithmetic;
+use crate::neural_network::dense_layer::DenseLayer;
+use crate::neural_network::errors::NnetError;
+use crate::neural_network::layer_type::LayerType;
+use crate::neural_network::nnet::Nnet;
+use crate::tensor_library::layout::Layout;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::parallel::MaybeSendSync;
+
+struct DenseSpec {
+    name : String,
+    outputs : usize,
+    // One row per output, None until weights() is called
+    weights : Option<Vec<Vec<i32>>>,
+    // One bias per output, None until biases() is called
+    biases : Option<Vec<i32>>,
+    activation : Option<Activation>,
+}
+
+pub struct Sequential<T> {
+    // The number of inputs of the first layer
+    inputs : Option<usize>,
+    layers : Vec<DenseSpec>,
+    // The first error which happened while building, it's returned by build()
+    error : Option<NnetError>,
+    _phantom : PhantomData<T>,
+}
+
+impl<T> Default for Sequential<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Sequential<T> {
+    /// Creates a model without layers.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::neural_network::nnet::Nnet;
+    /// use Cryptonic::neural_network::sequential::Sequential;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut network: Nnet<i32> = Sequential::new()
+    ///     .input(2)
+    ///     .dense(2).weights(vec![vec![1, -1], vec![2, 3]]).biases(vec![0, 1]).relu()
+    ///     .dense(1).weights(vec![vec![1, 1]]).biases(vec![0]).name("output")
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// // relu([1 - 2, 2 + 6 + 1]) = [0, 9]
+    /// let result = network.forward(Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor)).unwrap();
+    /// assert_eq!(result.data, vec![9]);
+    /// ```
+    pub fn new() -> Sequential<T> {
+        Sequential {
+            inputs : None,
+            layers : Vec::new(),
+            error : None,
+            _phantom : PhantomData,
+        }
+    }
+
+    /// Sets the number of inputs of the model, i.e. of its first layer.
+    pub fn input(mut self, inputs : usize) -> Self {
+        self.inputs = Some(inputs);
+        self
+    }
+
+    /// Adds a fully connected layer with the given number of outputs. Its number of inputs is the
+    /// number of outputs of the previous layer, or the one set with input() for the first layer.
+    /// The weights and biases have to be set with weights() and biases().
+    pub fn dense(mut self, outputs : usize) -> Self {
+        self.layers.push(DenseSpec {
+            name : format!("dense_{}", self.layers.len()),
+            outputs,
+            weights : None,
+            biases : None,
+            activation : None,
+        });
+        self
+    }
+
+    /// Sets the weights of the last layer, with one row per output and one column per input.
+    pub fn weights(self, weights : Vec<Vec<i32>>) -> Self {
+        self.with_last_layer(|layer| layer.weights = Some(weights))
+    }
+
+    /// Sets the biases of the last layer, with one bias per output.
+    pub fn biases(self, biases : Vec<i32>) -> Self {
+        self.with_last_layer(|layer| layer.biases = Some(biases))
+    }
+
+    /// Sets the activation of the last layer.
+    pub fn activation(self, activation : Activation) -> Self {
+        self.with_last_layer(|layer| layer.activation = Some(activation))
+    }
+
+    /// Sets ReLU as the activation of the last layer.
+    pub fn relu(self) -> Self {
+        self.activation(Activation::Relu)
+    }
+
+    /// Renames the last layer. The name is used in the errors about that layer.
+    pub fn name(self, name : &str) -> Self {
+        self.with_last_layer(|layer| layer.name = name.to_string())
+    }
+
+    fn with_last_layer<F>(mut self, func : F) -> Self where F : FnOnce(&mut DenseSpec) {
+        match self.layers.last_mut() {
+            Some(layer) => func(layer),
+            None => {
+                self.error.get_or_insert(NnetError::MissingLayer);
+            },
+        }
+        self
+    }
+}
+
+impl<T> Sequential<T> where T : Clone + Default + Debug + MaybeSendSync + CheckedArithmetic + Activate {
+    /// Checks that every layer has weights and biases which fit its number of inputs and outputs,
+    /// then creates the network. Returns NnetError::MissingInputSize if input() wasn't called,
+    /// NnetError::MissingWeights if a layer lacks its weights or biases and
+    /// NnetError::WeightCountMismatch if they don't fit.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::neural_network::errors::NnetError;
+    /// use Cryptonic::neural_network::sequential::Sequential;
+    /// let result = Sequential::<i32>::new()
+    ///     .input(3)
+    ///     .dense(2).weights(vec![vec![1, 0, 2], vec![-1, 1, 0]]).biases(vec![1, -4])
+    ///     .dense(1).weights(vec![vec![1, 1, 1]]).biases(vec![0]).name("output")
+    ///     .build();
+    /// assert_eq!(result.err(), Some(NnetError::WeightCountMismatch { layer: "output".to_string() }));
+    /// ```
+    pub fn build(self) -> Result<Nnet<T>, NnetError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        if self.layers.is_empty() {
+            return Err(NnetError::EmptyModel);
+        }
+        let mut inputs = self.inputs.ok_or(NnetError::MissingInputSize)?;
+
+        let mut network : Nnet<T> = Nnet::new();
+        let mut previous : Option<usize> = None;
+        for layer in self.layers {
+            let (weights, biases) = match (layer.weights, layer.biases) {
+                (Some(weights), Some(biases)) => (weights, biases),
+                _ => return Err(NnetError::MissingWeights { layer : layer.name }),
+            };
+            if weights.len() != layer.outputs || weights.iter().any(|row| row.len() != inputs) || biases.len() != layer.outputs {
+                return Err(NnetError::WeightCountMismatch { layer : layer.name });
+            }
+            let weights = Matrix::from_iter(vec![layer.outputs, inputs], weights.into_iter().flatten(), Layout::RowMajor);
+            let biases = Matrix::from_iter(vec![layer.outputs], biases, Layout::RowMajor);
+            let dense_layer = DenseLayer::new(&layer.name, weights, biases, layer.activation)?;
+
+            let id = network.add_layer(LayerType::DenseLayer(dense_layer));
+            network.set_name(id, &layer.name)?;
+            network.add_link(previous, Some(id))?;
+            previous = Some(id);
+            inputs = layer.outputs;
+        }
+        if let Some(id) = previous {
+            network.add_link(Some(id), None)?;
+        }
+        Ok(network)
+    }
+}
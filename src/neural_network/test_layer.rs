@@ -1,10 +1,11 @@
 use std::marker::PhantomData;
+use crate::neural_network::errors::NnetError;
 use crate::neural_network::layer_trait::Layer;
+use crate::tensor_library::layout::Layout;
 use crate::tensor_library::matrix::Matrix;
 
 /// This is layer for testing the neural network
-/// It is not for real use
-
+/// It is not for real use, its output is always zeros
 pub struct TestLayer<T> {
     input_shape : Vec<usize>,
     output_shape : Vec<usize>,
@@ -13,8 +14,15 @@ pub struct TestLayer<T> {
 impl<T> Layer for TestLayer<T> {
     type CType = T;
 
-    fn forward(&mut self, input : Matrix<T>) -> Matrix<Self::CType> where <Self as Layer>::CType: Clone + Default {
-        todo!()
+    fn forward(&mut self, input : Matrix<T>) -> Result<Matrix<Self::CType>, NnetError> where <Self as Layer>::CType: Clone + Default {
+        if input.shape() != &self.input_shape {
+            return Err(NnetError::ShapeMismatch {
+                layer: "TestLayer".to_string(),
+                expected: self.input_shape.clone(),
+                found: input.shape().clone(),
+            });
+        }
+        Ok(Matrix::new(self.output_shape.clone(), Layout::RowMajor))
     }
 
     fn get_input_shape(&self) -> &Vec<usize> {
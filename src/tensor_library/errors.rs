@@ -14,6 +14,8 @@ pub enum MatrixError {
     ShapeError,
     OutOfBounds,
     ReshapeError,
+    SerializationError,
+    IoError,
     NotImplementedError
 }
 
@@ -28,11 +30,13 @@ impl Display for MatrixError {
             MatrixError::DimError => write!(f, "Matrix cannot be operated on over the given dimension"),
             MatrixError::MatmulShapeError => write!(
                 f,
-                "Matrix must have at least two dimensions and have same shape in all dims except the last dimension"
+                "Inner dimensions of the matrices don't match or their batch dimensions aren't broadcastable"
             ),
             MatrixError::ShapeError => write!(f, "Matrix must have the same shape in all dims except the last dimension"),
             MatrixError::OutOfBounds => write!(f, "Indices are out of bounds for the matrix"),
             MatrixError::ReshapeError => write!(f, "Matrix cannot be reshaped into given shape"),
+            MatrixError::SerializationError => write!(f, "Matrix cannot be serialized or the serialized data is invalid"),
+            MatrixError::IoError => write!(f, "Matrix cannot be read from or written to the file"),
             MatrixError::NotImplementedError => write!(f, "Method or function not implemented")
         }
     }
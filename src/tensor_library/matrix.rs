@@ -1,14 +1,19 @@
 #![allow(unused_assignments)]
 
 use std::fmt::{Debug, Display};
-use std::ops::{Add, AddAssign, Mul, MulAssign, Sub};
+use std::ops::{Add, AddAssign, Div, Mul, MulAssign, Rem, Sub};
 // use crate::cryptography::type_traits::{MyAdd, MyMul};
+use serde::{Deserialize, Serialize};
 use crate::tensor_library::errors::MatrixError;
 use crate::tensor_library::layout::Layout;
-use crate::tensor_library::utils::{calc_concat_shape, calc_strides_from_shape, check_concat_dims};
+use crate::tensor_library::ops::zip_with;
+use crate::tensor_library::parallel::{collect_indexed, for_each_mut, MaybeSendSync};
+use crate::tensor_library::serialization::MatrixRepr;
+use crate::tensor_library::utils::{calc_concat_shape, calc_idx_from_position, calc_next_idx, calc_strides_from_shape, check_concat_dims};
 
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "MatrixRepr<T>")]
 pub struct Matrix<T> where T: Clone + Default + 'static{
     pub shape: Vec<usize>,
     pub strides: Vec<usize>,
@@ -46,7 +51,6 @@ impl<T> Matrix<T> where T: Clone + Default {
     ///
     /// # Example
     /// ```
-    /// use Cryptonic::{matrix::Matrix, layout::Layout};
     /// use Cryptonic::tensor_library::layout::Layout;
     /// use Cryptonic::tensor_library::matrix::Matrix;
     /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 0.., Layout::RowMajor);
@@ -125,7 +129,9 @@ impl<T> Matrix<T>  where T: Clone + Default {
         &self.shape
     }
 
-    /// Reshapes the matrix if possible.
+    /// Reshapes the matrix if possible. The elements keep the order given by the layout, e.g. for
+    /// a row major matrix the last index changes the fastest. Non-contiguous matrices (e.g. after
+    /// a transpose()) get copied into that order first.
     ///
     /// # Examples
     /// ```
@@ -138,10 +144,14 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// let l = mat.reshape(&vec![20, 5]);
     /// assert_eq!(mat.shape(), &vec![20, 5]);
     /// ```
-    pub fn reshape(&mut self, new_shape: &Vec<usize>) -> Result<(), MatrixError> {
+    pub fn reshape(&mut self, new_shape: &[usize]) -> Result<(), MatrixError> {
         let size: usize = new_shape.iter().copied().reduce(|a, b| a * b).unwrap();
         if size == self.size {
-            self.shape = new_shape.clone();
+            // The new strides are calculated from the layout, so the data has to be in that order
+            if !self.is_contiguous() {
+                *self = self.to_contiguous(self.layout);
+            }
+            self.shape = new_shape.to_vec();
             self.strides = calc_strides_from_shape(new_shape, self.layout);
             Ok(())
         } else {
@@ -218,7 +228,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// println!("{:?}", mat.check_bounds(&vec![3, 4]).err()); // Prints Error because !3<3 && !4<4
     /// println!("{:?}", mat.check_bounds(&vec![2, 3]).unwrap()); // Prints () because 2<3 && 3<4
     /// ```
-    pub fn check_bounds(&self, idx: &Vec<usize>) -> Result<bool, MatrixError> {
+    pub fn check_bounds(&self, idx: &[usize]) -> Result<bool, MatrixError> {
         if idx.len() != self.shape.len() {
             return Err(MatrixError::DimError);
         }
@@ -240,7 +250,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// let mut mat: Matrix<i32> = Matrix::new(vec![3, 4], Layout::RowMajor);
     /// println!("{}", mat.get_physical_idx(&vec![2, 1]).unwrap()); // Prints 9, because 9 = 2*4 + 1*1, since strides = [4, 1]
     /// ```
-    pub fn get_physical_idx(&self, idx: &Vec<usize>) -> Result<usize, MatrixError> {
+    pub fn get_physical_idx(&self, idx: &[usize]) -> Result<usize, MatrixError> {
         let mut return_val: usize = 0;
         match self.check_bounds(idx) {
             Ok(_) => {
@@ -269,7 +279,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// println!("{:?}", mat.get(&vec![5, 6]).err()); // prints Error because self.get_physical_idx() fails
     /// ```
     // TODO: Add slicing
-    pub fn get(&self, idx: &Vec<usize>) -> Result<&T, MatrixError> {
+    pub fn get(&self, idx: &[usize]) -> Result<&T, MatrixError> {
         match self.get_physical_idx(idx) {
             Ok(physical_idx) => Ok(&self.data[physical_idx]),
             Err(m_err) => Err(m_err),
@@ -280,7 +290,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// I haven't included examples
     ///
     // TODO: Add slicing
-    pub fn get_copy(&self, idx: &Vec<usize>) -> Result<T, MatrixError> {
+    pub fn get_copy(&self, idx: &[usize]) -> Result<T, MatrixError> {
         match self.get_physical_idx(idx) {
             Ok(physical_idx) => Ok(self.data[physical_idx].clone()),
             Err(m_err) => Err(m_err),
@@ -291,7 +301,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// I haven't included examples
     ///
     // TODO: Add slicing
-    pub fn get_mut(&mut self, idx: &Vec<usize>) -> Result<&mut T, MatrixError> {
+    pub fn get_mut(&mut self, idx: &[usize]) -> Result<&mut T, MatrixError> {
         match self.get_physical_idx(idx) {
             Ok(physical_idx) => Ok(&mut self.data[physical_idx]),
             Err(m_err) => Err(m_err),
@@ -313,7 +323,7 @@ impl<T> Matrix<T>  where T: Clone + Default {
     /// println!("{}", mat.get(&vec![0,0]).unwrap()); // print 5
     /// ```
     // TODO: Add slicing
-    pub fn set(&mut self, idx: &Vec<usize>, value: T) -> Result<(), MatrixError> {
+    pub fn set(&mut self, idx: &[usize], value: T) -> Result<(), MatrixError> {
         match self.get_mut(idx) {
             Ok(cell) => {
                 *cell = value;
@@ -389,49 +399,325 @@ impl<T> Matrix<T>  where T: Clone + Default {
     pub fn apply_mut<F: FnMut(&mut T)>(&mut self, func: F) {
         self.data.iter_mut().for_each(func);
     }
+
+    /// Same as apply_mut(), but with the `parallel` feature the cells are split between threads,
+    /// which is why the function can't have mutable state. Without the feature it's the same as
+    /// apply_mut().
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1..,Layout::RowMajor);
+    /// mat.par_apply_mut(|n| *n *= 2);
+    ///
+    /// assert_eq!(mat.data, vec![2, 4, 6, 8]);
+    /// ```
+    pub fn par_apply_mut<F: Fn(&mut T) + MaybeSendSync>(&mut self, func: F) where T: MaybeSendSync {
+        for_each_mut(&mut self.data, func);
+    }
+}
+
+// Implements the reduction methods. All of them reduce the matrix along a single axis. If
+// keepdims is true the reduced axis is kept with size 1, otherwise it's removed from the shape.
+// Since a matrix can't have an empty shape, reducing a one-dimensional matrix without keepdims
+// results in shape [1]. Reducing along an axis of size 0 returns MatrixError::DimError unless the
+// result is empty anyway.
+impl<T> Matrix<T>  where T: Clone + Default {
+    /// This is a utilities function on which all reductions are built. For every position of the
+    /// result the elements along the axis are folded into an accumulator. The accumulator is
+    /// created from the first element by init and func is called for the rest of them together
+    /// with their index along the axis.
+    fn fold_axis<A, I, F>(&self, axis: usize, keepdims: bool, mut init: I, mut func: F) -> Result<Matrix<A>, MatrixError>
+        where
+            A: Clone + Default,
+            I: FnMut(&T) -> A,
+            F: FnMut(A, usize, &T) -> A,
+    {
+        if axis >= self.shape.len() {
+            return Err(MatrixError::DimError);
+        }
+        let mut kept_shape = self.shape.clone();
+        kept_shape[axis] = 1;
+        let kept_size: usize = kept_shape.iter().product();
+        // There is no first element to create the accumulator from
+        if self.shape[axis] == 0 && kept_size > 0 {
+            return Err(MatrixError::DimError);
+        }
+        let mut data: Vec<A> = Vec::with_capacity(kept_size);
+
+        let mut idx = if kept_size == 0 { None } else { Some(vec![0; kept_shape.len()]) };
+        while let Some(i) = idx {
+            // i[axis] is always 0 here, so the offset points to the first element along the axis
+            let offset: usize = i.iter().zip(self.strides.iter()).map(|(i, stride)| i * stride).sum();
+            let mut acc = init(&self.data[offset]);
+            for k in 1..self.shape[axis] {
+                acc = func(acc, k, &self.data[offset + k * self.strides[axis]]);
+            }
+            data.push(acc);
+            idx = calc_next_idx(&kept_shape, &i);
+        }
+
+        if !keepdims {
+            kept_shape.remove(axis);
+            if kept_shape.is_empty() {
+                kept_shape.push(1);
+            }
+        }
+        Ok(Matrix::from_iter(kept_shape, data, Layout::RowMajor))
+    }
+
+    /// Reduces the matrix along the axis with the given function. The function gets the result so
+    /// far and the next element and returns the new result.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+    ///
+    /// let result = mat.reduce(1, false, |acc, n| acc.max(*n)).unwrap();
+    /// assert_eq!(result.data, vec![3, 6]);
+    /// ```
+    pub fn reduce<F>(&self, axis: usize, keepdims: bool, mut func: F) -> Result<Matrix<T>, MatrixError> where F: FnMut(T, &T) -> T {
+        self.fold_axis(axis, keepdims, |first| first.clone(), |acc, _, item| func(acc, item))
+    }
+
+    /// Returns the sum of the elements along the axis.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::errors::MatrixError;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+    ///
+    /// let result = mat.sum(0, false).unwrap();
+    /// assert_eq!(result.shape(), &vec![3]);
+    /// assert_eq!(result.data, vec![5, 7, 9]);
+    ///
+    /// let result = mat.sum(1, true).unwrap();
+    /// assert_eq!(result.shape(), &vec![2, 1]);
+    /// assert_eq!(result.data, vec![6, 15]);
+    ///
+    /// assert_eq!(Err(MatrixError::DimError), mat.sum(2, false).map(|m| m.data));
+    /// ```
+    pub fn sum(&self, axis: usize, keepdims: bool) -> Result<Matrix<T>, MatrixError> where T: Add<Output = T> {
+        self.reduce(axis, keepdims, |acc, item| acc + item.clone())
+    }
+
+    /// Returns the product of the elements along the axis. Same as self.sum(), but multiplies.
+    pub fn prod(&self, axis: usize, keepdims: bool) -> Result<Matrix<T>, MatrixError> where T: Mul<Output = T> {
+        self.reduce(axis, keepdims, |acc, item| acc * item.clone())
+    }
+
+    /// Returns the largest element along the axis.
+    pub fn max(&self, axis: usize, keepdims: bool) -> Result<Matrix<T>, MatrixError> where T: PartialOrd {
+        self.reduce(axis, keepdims, |acc, item| if *item > acc { item.clone() } else { acc })
+    }
+
+    /// Returns the smallest element along the axis.
+    pub fn min(&self, axis: usize, keepdims: bool) -> Result<Matrix<T>, MatrixError> where T: PartialOrd {
+        self.reduce(axis, keepdims, |acc, item| if *item < acc { item.clone() } else { acc })
+    }
+
+    /// Returns the index of the largest element along the axis. If the largest element occurs
+    /// more than once, the index of the first occurrence is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// // Classifier outputs for two samples and three classes
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], vec![1, 7, 3, 9, 2, 9], Layout::RowMajor);
+    ///
+    /// let labels = mat.argmax(1, false).unwrap();
+    /// assert_eq!(labels.data, vec![1, 0]);
+    /// ```
+    pub fn argmax(&self, axis: usize, keepdims: bool) -> Result<Matrix<usize>, MatrixError> where T: PartialOrd {
+        let result = self.fold_axis(
+            axis,
+            keepdims,
+            |first| (0, first.clone()),
+            |(max_idx, max), k, item| if *item > max { (k, item.clone()) } else { (max_idx, max) },
+        )?;
+        Ok(Matrix::from_iter(result.shape.clone(), result.data.into_iter().map(|(idx, _)| idx), Layout::RowMajor))
+    }
+
+    /// Returns the mean of the elements along the axis. The division is done in T, so for
+    /// integer types the result is rounded the same way as the integer division of T.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<f64> = Matrix::from_iter(vec![2, 2], vec![1.0, 2.0, 4.0, 8.0], Layout::RowMajor);
+    ///
+    /// let result = mat.mean(0, false).unwrap();
+    /// assert_eq!(result.data, vec![2.5, 5.0]);
+    /// ```
+    pub fn mean(&self, axis: usize, keepdims: bool) -> Result<Matrix<T>, MatrixError> where T: MaybeSendSync + Add<Output = T> + Div<Output = T> + From<u8> {
+        let mut result = self.sum(axis, keepdims)?;
+        // The length of the axis is built by adding ones, so that any type which can be created
+        // from a u8 can be used, no matter how long the axis is.
+        let count = (1..self.shape[axis]).fold(T::from(1), |acc, _| acc + T::from(1));
+        result.par_apply_mut(|item| *item = item.clone() / count.clone());
+        Ok(result)
+    }
 }
 
 // Implements structure changing methods
 impl<T> Matrix<T>  where T: Clone + Default {
-    /// Transposes the matrix. Reverses the shape, strides and in turn switches the layout.
+    /// Transposes the matrix by reversing the shape and the strides. No data gets moved, so
+    /// afterwards the matrix usually isn't contiguous. The layout stays the same, so reshape() and
+    /// flatten() see the elements in the transposed order.
     ///
     /// # Examples
     /// ```
-    /// // Modify all cells with a function
     /// use Cryptonic::tensor_library::layout::Layout;
     /// use Cryptonic::tensor_library::matrix::Matrix;
     /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 1..,Layout::RowMajor);
     /// mat.transpose();
     ///
-    /// println!("{:?}", mat.shape()); // prints [4, 3]
-    /// println!("{:?}", mat.strides()); // prints [4, 3]
-    /// println!("{:?}", mat.layout); // prints Layout::ColumnMajor
+    /// assert_eq!(mat.shape(), &vec![4, 3]);
+    /// assert_eq!(mat.strides(), &vec![1, 4]);
+    /// assert_eq!(mat.layout, Layout::RowMajor);
+    /// assert!(!mat.is_contiguous());
     /// ```
     pub fn transpose(&mut self) {
         self.shape.reverse();
         self.strides.reverse();
-        match self.layout {
-            Layout::RowMajor => self.layout = Layout::ColumnMajor,
-            Layout::ColumnMajor => self.layout = Layout::RowMajor,
+    }
+
+    /// Reorders the dimensions of the matrix, so that dimension i of the result is dimension
+    /// axes[i] of the original matrix. Like transpose() only the shape and the strides change.
+    /// Returns MatrixError::DimError if axes isn't a permutation of the dimensions.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3, 4], 0.., Layout::RowMajor);
+    /// mat.permute(&[2, 0, 1]).unwrap();
+    ///
+    /// assert_eq!(mat.shape(), &vec![4, 2, 3]);
+    /// assert_eq!(mat.get(&[3, 1, 2]), Ok(&23));
+    /// ```
+    pub fn permute(&mut self, axes: &[usize]) -> Result<(), MatrixError> {
+        if axes.len() != self.shape.len() {
+            return Err(MatrixError::DimError);
+        }
+        for (i, axis) in axes.iter().enumerate() {
+            if *axis >= self.shape.len() || axes[..i].contains(axis) {
+                return Err(MatrixError::DimError);
+            }
         }
+        self.shape = axes.iter().map(|axis| self.shape[*axis]).collect();
+        self.strides = axes.iter().map(|axis| self.strides[*axis]).collect();
+        Ok(())
     }
 
-    /// Transposes the matrix. Reverses the shape, strides and in turn switches the layout.
+    /// Swaps two dimensions of the matrix. Returns MatrixError::DimError if either of them is out
+    /// of range.
     ///
     /// # Examples
     /// ```
-    /// // Modify all cells with a function
     /// use Cryptonic::tensor_library::layout::Layout;
     /// use Cryptonic::tensor_library::matrix::Matrix;
-    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 1..,Layout::RowMajor);
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3, 4], 0.., Layout::RowMajor);
+    /// mat.swap_axes(0, 2).unwrap();
+    ///
+    /// assert_eq!(mat.shape(), &vec![4, 3, 2]);
+    /// assert_eq!(mat.get(&[3, 1, 0]), Ok(&7));
+    /// ```
+    pub fn swap_axes(&mut self, axis1: usize, axis2: usize) -> Result<(), MatrixError> {
+        if axis1 >= self.shape.len() || axis2 >= self.shape.len() {
+            return Err(MatrixError::DimError);
+        }
+        self.shape.swap(axis1, axis2);
+        self.strides.swap(axis1, axis2);
+        Ok(())
+    }
+
+    /// Returns whether the data holds exactly the elements of the matrix in the order given by
+    /// its layout. This stops being the case after transpose(), permute(), swap_axes() or
+    /// set_strides(). The strides of dimensions of size 1 never get used, so they're ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+    /// assert!(mat.is_contiguous());
+    ///
+    /// mat.transpose();
+    /// assert!(!mat.is_contiguous());
+    /// assert!(mat.to_contiguous(Layout::RowMajor).is_contiguous());
+    /// ```
+    pub fn is_contiguous(&self) -> bool {
+        let contiguous_strides = calc_strides_from_shape(&self.shape, self.layout);
+        self.data.len() == self.size && self.shape
+            .iter()
+            .zip(self.strides.iter().zip(contiguous_strides.iter()))
+            .all(|(dim, (stride, contiguous_stride))| *dim == 1 || stride == contiguous_stride)
+    }
+
+    /// Returns a copy of the matrix whose data is physically reordered, so that it's contiguous
+    /// in the given layout. The elements are the same at every index.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+    /// mat.transpose();
+    ///
+    /// let contiguous = mat.to_contiguous(Layout::RowMajor);
+    /// assert_eq!(contiguous.data, vec![1, 4, 2, 5, 3, 6]);
+    /// assert_eq!(contiguous.get(&[2, 1]), mat.get(&[2, 1]));
+    ///
+    /// let contiguous = mat.to_contiguous(Layout::ColumnMajor);
+    /// assert_eq!(contiguous.data, vec![1, 2, 3, 4, 5, 6]);
+    /// ```
+    pub fn to_contiguous(&self, layout: Layout) -> Matrix<T> {
+        let mut result: Matrix<T> = Matrix::new(self.shape.clone(), layout);
+        let mut idx = if self.size == 0 { None } else { Some(vec![0; self.shape.len()]) };
+        while let Some(i) = idx {
+            let src: usize = i.iter().zip(self.strides.iter()).map(|(i, stride)| i * stride).sum();
+            let dst: usize = i.iter().zip(result.strides.iter()).map(|(i, stride)| i * stride).sum();
+            result.data[dst] = self.data[src].clone();
+            idx = calc_next_idx(&self.shape, &i);
+        }
+        result
+    }
+
+    /// Returns the matrix itself if it's contiguous, otherwise a contiguous copy in the same
+    /// layout. Used by the functions which compute the strides from the shape and the layout.
+    pub(crate) fn into_contiguous(self) -> Matrix<T> {
+        if self.is_contiguous() {
+            self
+        } else {
+            self.to_contiguous(self.layout)
+        }
+    }
+
+    /// Flattens the matrix to one dimension. The elements are in the order given by the layout,
+    /// so after a transpose() the data gets reordered first.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1..,Layout::RowMajor);
+    /// mat.transpose();
     /// mat.flatten();
     ///
-    /// println!("{:?}", mat.shape()); // prints [12]
-    /// println!("{:?}", mat.strides()); // prints [1]
-    /// println!("{:?}", mat.layout); // prints Layout::ColumnMajor
+    /// assert_eq!(mat.shape(), &vec![6]);
+    /// assert_eq!(mat.strides(), &vec![1]);
+    /// assert_eq!(mat.data, vec![1, 4, 2, 5, 3, 6]);
     /// ```
     pub fn flatten(&mut self){
-        match self.reshape(&vec![self.size()]) {
+        match self.reshape(&[self.size()]) {
             Ok(_) => {},
             Err(err) => panic!("{}", err)
         }
@@ -464,23 +750,23 @@ impl<T> Matrix<T>  where T: Clone + Default {
 type BroadcastRetType = Result<(Vec<usize>, Vec<usize>, Vec<usize>), MatrixError>;
 
 pub fn broadcast(
-    lhs_shape: &Vec<usize>,
+    lhs_shape: &[usize],
     lhs_layout: Layout,
-    rhs_shape: &Vec<usize>,
+    rhs_shape: &[usize],
     rhs_layout: Layout,
 ) -> BroadcastRetType {
     let lhs_shape = if lhs_shape.len() < rhs_shape.len() {
         let ones = vec![1; rhs_shape.len() - lhs_shape.len()];
-        [&ones[..], &lhs_shape[..]].concat()
+        [&ones[..], lhs_shape].concat()
     } else {
-        lhs_shape.clone()
+        lhs_shape.to_vec()
     };
 
     let rhs_shape = if rhs_shape.len() < lhs_shape.len() {
         let ones = vec![1; lhs_shape.len() - rhs_shape.len()];
-        [&ones[..], &rhs_shape[..]].concat()
+        [&ones[..], rhs_shape].concat()
     } else {
-        rhs_shape.clone()
+        rhs_shape.to_vec()
     };
 
     let mut broadcasted_shape: Vec<usize> = Vec::with_capacity(lhs_shape.len());
@@ -617,46 +903,8 @@ pub fn concat<T>(lhs: Matrix<T>, rhs: Matrix<T>, axis: usize) -> ConcatRetType<T
 /// This was added due to clippy warnings
 type SubRetType<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
 
-pub fn subtract<T>(mut lhs: Matrix<T>,mut rhs: Matrix<T>) -> SubRetType<T> where T: Clone + Default + Sub + Sub<Output = T>, <T as Sub>::Output: Clone + Default{
-    let mut final_shape: Vec<usize> = vec![];
-    match broadcast(lhs.shape(), lhs.layout, rhs.shape(), rhs.layout) {
-        Ok((_shape, _lhs_strides, _rhs_strides)) => {
-            lhs.set_shape(&_shape);
-            rhs.set_shape(&_shape);
-            lhs.set_strides(&_lhs_strides);
-            rhs.set_strides(&_rhs_strides);
-            final_shape = _shape;
-        },
-        Err(err) => {
-            return Err(err);
-        }
-    }
-
-    let lhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &lhs,
-        index: vec![0; lhs.shape().len()],
-        current_el: None,
-        empty: false,
-    };
-    let rhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &rhs,
-        index: vec![0; rhs.shape().len()],
-        current_el: None,
-        empty: false,
-    };
-
-    let mut new_matrix = Matrix::new(final_shape, Layout::RowMajor);
-
-    for ((lhs_item, lhs_index), (rhs_item, rhs_index)) in lhs_iter.zip(rhs_iter){
-        assert_eq!(lhs_index, rhs_index);
-        match new_matrix.set(&lhs_index, lhs_item - rhs_item) {
-            Ok(_) => {},
-            Err(err) => {
-                return Err(err);
-            }
-        }
-    }
-    Ok((new_matrix, lhs, rhs))
+pub fn subtract<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> SubRetType<T> where T: Clone + Default + MaybeSendSync + Sub + Sub<Output = T>, <T as Sub>::Output: Clone + Default{
+    try_broadcast_op(lhs, rhs, |lhs_item, rhs_item| Ok(lhs_item - rhs_item))
 }
 
 
@@ -692,46 +940,8 @@ pub fn subtract<T>(mut lhs: Matrix<T>,mut rhs: Matrix<T>) -> SubRetType<T> where
 /// This was added due to clippy warnings
 type AddRetType<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
 
-pub fn add<T>(mut lhs: Matrix<T>,mut rhs: Matrix<T>) -> AddRetType<T> where T: Clone + Default + Add + Add<Output = T>, <T as Add>::Output: Clone + Default{
-    let mut final_shape: Vec<usize> = vec![];
-    match broadcast(lhs.shape(), lhs.layout, rhs.shape(), rhs.layout) {
-        Ok((_shape, _lhs_strides, _rhs_strides)) => {
-            lhs.set_shape(&_shape);
-            rhs.set_shape(&_shape);
-            lhs.set_strides(&_lhs_strides);
-            rhs.set_strides(&_rhs_strides);
-            final_shape = _shape;
-        },
-        Err(err) => {
-            return Err(err);
-        }
-    }
-
-    let lhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &lhs,
-        index: vec![0; lhs.shape().len()],
-        current_el: None,
-        empty: false,
-    };
-    let rhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &rhs,
-        index: vec![0; rhs.shape().len()],
-        current_el: None,
-        empty: false,
-    };
-
-    let mut new_matrix = Matrix::new(final_shape, Layout::RowMajor);
-
-    for ((lhs_item, lhs_index), (rhs_item, rhs_index)) in lhs_iter.zip(rhs_iter){
-        assert_eq!(lhs_index, rhs_index);
-        match new_matrix.set(&lhs_index, lhs_item + rhs_item) {
-            Ok(_) => {},
-            Err(err) => {
-                return Err(err);
-            }
-        }
-    }
-    Ok((new_matrix, lhs, rhs))
+pub fn add<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> AddRetType<T> where T: Clone + Default + MaybeSendSync + Add + Add<Output = T>, <T as Add>::Output: Clone + Default{
+    try_broadcast_op(lhs, rhs, |lhs_item, rhs_item| Ok(lhs_item + rhs_item))
 }
 
 
@@ -771,66 +981,58 @@ pub fn add<T>(mut lhs: Matrix<T>,mut rhs: Matrix<T>) -> AddRetType<T> where T: C
 ///     }
 /// ```
 ///
-pub fn multiply_scalar<T>(mut lhs: Matrix<T>, rhs: T) -> Matrix<T> where T: Clone + Default + Mul + Mul<Output = T> + MulAssign, <T as Mul>::Output: Clone + Default{
-    for i in 0..lhs.data.len(){
-        lhs.data[i] = lhs.data[i].clone() * rhs.clone();
-    }
+pub fn multiply_scalar<T>(mut lhs: Matrix<T>, rhs: T) -> Matrix<T> where T: Clone + Default + MaybeSendSync + Mul + Mul<Output = T> + MulAssign, <T as Mul>::Output: Clone + Default{
+    for_each_mut(&mut lhs.data, |item| *item = item.clone() * rhs.clone());
     lhs
 }
 
-pub fn multiply_scalar_generic<T>(mut lhs: Matrix<i32>, rhs: T) -> Matrix<T> where T: Clone + Default + Mul<i32, Output = T>, i32: Mul<T>{
-    let mut new_matrix: Matrix<T> = Matrix::new(lhs.shape().clone(), lhs.layout.clone());
-    for i in 0..lhs.data.len(){
-        new_matrix.data[i] = rhs.clone() * lhs.data[i].clone();
-    }
+pub fn multiply_scalar_generic<T>(lhs: Matrix<i32>, rhs: T) -> Matrix<T> where T: Clone + Default + MaybeSendSync + Mul<i32, Output = T>, i32: Mul<T>{
+    let lhs = lhs.into_contiguous();
+    let mut new_matrix: Matrix<T> = Matrix::new(lhs.shape().clone(), lhs.layout);
+    new_matrix.data = collect_indexed(lhs.data.len(), |i| rhs.clone() * lhs.data[i]);
     new_matrix
 }
 
 
 /// Given a matrix and a scalar we return the result of the multiplication of every element of the
 /// matrix with the  of a different type.
-pub fn multiply_scalar_diff_type<T>(mut lhs: Matrix<T>, rhs: i32) -> Matrix<T> where T: Clone + Default + Mul<i32, Output = T>{
-    for i in 0..lhs.data.len(){
-        lhs.data[i] = lhs.data[i].clone() * rhs.clone();
-    }
+pub fn multiply_scalar_diff_type<T>(mut lhs: Matrix<T>, rhs: i32) -> Matrix<T> where T: Clone + Default + MaybeSendSync + Mul<i32, Output = T>{
+    for_each_mut(&mut lhs.data, |item| *item = item.clone() * rhs);
     lhs
 }
 
-/*
-/// Given a two matrices we return the result of the multiplication of them. The API user should be
-/// careful of the fact that we change the shape and strides of the given matrices so if he needs
-/// them to be a different shape he should reshape them after the operation.
+/// Given two matrices the function first checks if they're broadcastable. The broadcast function
+/// takes care of any dimensional issues. After we have broadcasted the matrices we then iterate
+/// through them and multiply the elements, i.e. this is the element-wise(Hadamard) product. For
+/// the matrix product use matmul().
 ///
 /// The method takes ownership of rhs and lhs for it's duration and then returns it. In the future
 /// we'll most likely add a feature to take them by reference.
 /// # Examples
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, multiply};
+/// let mut lhs: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1..,Layout::RowMajor);
+/// let mut rhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![0, 1],Layout::RowMajor);
 ///
-///
-// use Cryptonic::{layout::Layout, matrix::*};
-// let mut lhs: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1..,Layout::RowMajor);
-// let mut lhs_dup: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1..,Layout::RowMajor);
-// let mut x = multiply_scalar(lhs, 5);
-//
-//     let mut matrix_iter = MatrixIter {
-//         mat: &x,
-//         index: vec![0; x.shape().len()],
-//         current_el: None,
-//         empty: false,
-//     };
-//
-//     let mut matrix_iter_dup = MatrixIter {
-//         mat: &lhs_dup,
-//         index: vec![0; x.shape().len()],
-//         current_el: None,
-//         empty: false,
-//     };
-//
-//     for ((item, idx), (item_2, idx_2)) in matrix_iter.zip(matrix_iter_dup) {
-//         assert_eq!(item, item_2 * 5) // Should return all 5, 10, 15, etc.
-//     }
-///
-///
-pub fn multiply<T>(mut lhs: Matrix<T>, mut rhs: Matrix<T>) -> Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError> where T: Clone + Default + Mul + Mul<Output = T> + MulAssign, <T as Mul>::Output: Clone + Default{
+/// // Masks the first column
+/// let (val, _lhs, _rhs) = multiply(lhs, rhs).unwrap();
+/// assert_eq!(val.data, vec![0, 2, 0, 4]);
+/// ```
+/// This was added due to clippy warnings
+type MulRetType<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
+
+pub fn multiply<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> MulRetType<T> where T: Clone + Default + MaybeSendSync + Mul + Mul<Output = T>, <T as Mul>::Output: Clone + Default{
+    try_broadcast_op(lhs, rhs, |lhs_item, rhs_item| Ok(lhs_item * rhs_item))
+}
+
+/// Given two matrices the function first checks if they're broadcastable. After we have
+/// broadcasted the matrices we then iterate through them and apply func to the elements. This is
+/// used by divide() and remainder(), whose operations can fail.
+fn try_broadcast_op<T, F>(mut lhs: Matrix<T>, mut rhs: Matrix<T>, func: F) -> DivRetType<T> where T: Clone + Default + MaybeSendSync, F: Fn(T, T) -> Result<T, MatrixError> + MaybeSendSync{
+    // broadcast() calculates the strides from the layout, so the data has to be in that order
+    lhs = lhs.into_contiguous();
+    rhs = rhs.into_contiguous();
     let mut final_shape: Vec<usize> = vec![];
     match broadcast(lhs.shape(), lhs.layout, rhs.shape(), rhs.layout) {
         Ok((_shape, _lhs_strides, _rhs_strides)) => {
@@ -844,42 +1046,241 @@ pub fn multiply<T>(mut lhs: Matrix<T>, mut rhs: Matrix<T>) -> Result<(Matrix<T>,
             return Err(err);
         }
     }
-    /*
-    let lhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &lhs,
-        index: vec![0; lhs.shape().len()],
-        current_el: None,
-        empty: false,
+
+    // Every element of the result is computed on its own, so this can run across threads
+    let results = collect_indexed(final_shape.iter().product(), |position| {
+        let idx = calc_idx_from_position(&final_shape, position);
+        let lhs_offset: usize = idx.iter().zip(lhs.strides.iter()).map(|(i, stride)| i * stride).sum();
+        let rhs_offset: usize = idx.iter().zip(rhs.strides.iter()).map(|(i, stride)| i * stride).sum();
+        func(lhs.data[lhs_offset].clone(), rhs.data[rhs_offset].clone())
+    });
+    let data: Vec<T> = match results.into_iter().collect() {
+        Ok(data) => data,
+        Err(err) => {
+            return Err(err);
+        }
     };
-    let rhs_iter: MatrixIter<T> = MatrixIter {
-        mat: &rhs,
-        index: vec![0; rhs.shape().len()],
-        current_el: None,
-        empty: false,
+    Ok((Matrix::from_iter(final_shape, data, Layout::RowMajor), lhs, rhs))
+}
+
+/// Given two matrices of an integer type the function broadcasts them and divides the elements
+/// of lhs by the elements of rhs. The division is the one of T, so integers are rounded towards
+/// zero.
+///
+/// Returns MatrixError::OpError if an element of rhs is 0(i.e. T::default()) instead of
+/// panicking like integer division does.
+///
+/// The method takes ownership of rhs and lhs for it's duration and then returns it. In the future
+/// we'll most likely add a feature to take them by reference.
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::errors::MatrixError;
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{divide, Matrix};
+/// let lhs: Matrix<i32> = Matrix::from_iter(vec![2, 2], vec![10, 20, 30, 40], Layout::RowMajor);
+/// let rhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![3, 10], Layout::RowMajor);
+///
+/// let (val, lhs, _rhs) = divide(lhs, rhs).unwrap();
+/// assert_eq!(val.data, vec![3, 2, 10, 4]);
+///
+/// let zeros: Matrix<i32> = Matrix::new(vec![2], Layout::RowMajor);
+/// assert_eq!(Err(MatrixError::OpError), divide(lhs, zeros).map(|(val, _, _)| val.data));
+/// ```
+/// This was added due to clippy warnings
+type DivRetType<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
+
+pub fn divide<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> DivRetType<T> where T: Clone + Default + MaybeSendSync + PartialEq + Div<Output = T>{
+    try_broadcast_op(lhs, rhs, |lhs_item, rhs_item| {
+        if rhs_item == T::default() {
+            return Err(MatrixError::OpError);
+        }
+        Ok(lhs_item / rhs_item)
+    })
+}
+
+/// Same as divide(), but returns the remainders of the divisions. The sign of the remainder is
+/// the one of the % operator of T, i.e. the sign of lhs for the primitive integers.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, remainder};
+/// let lhs: Matrix<i32> = Matrix::from_iter(vec![4], vec![7, -7, 8, 9], Layout::RowMajor);
+/// let rhs: Matrix<i32> = Matrix::from_iter(vec![1], vec![4], Layout::RowMajor);
+///
+/// let (val, _lhs, _rhs) = remainder(lhs, rhs).unwrap();
+/// assert_eq!(val.data, vec![3, -3, 0, 1]);
+/// ```
+/// This was added due to clippy warnings
+type RemRetType<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
+
+pub fn remainder<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> RemRetType<T> where T: Clone + Default + MaybeSendSync + PartialEq + Rem<Output = T>{
+    try_broadcast_op(lhs, rhs, |lhs_item, rhs_item| {
+        if rhs_item == T::default() {
+            return Err(MatrixError::OpError);
+        }
+        Ok(lhs_item % rhs_item)
+    })
+}
+
+
+
+/// Given two matrices we return their matrix product. The rules are the same as the ones of
+/// NumPy's `@` operator:
+/// - If both matrices are two-dimensional they get multiplied like conventional matrices, so a
+///   MxK matrix times a KxN matrix results in a MxN matrix.
+/// - If either of them has more than two dimensions it's treated as a stack of matrices residing
+///   in the last two dimensions. The leading(batch) dimensions are broadcasted against each other
+///   with the broadcast() function.
+/// - If lhs is one-dimensional it's promoted to a matrix by prepending a 1 to its shape and if
+///   rhs is one-dimensional it's promoted by appending a 1 to its shape. After the multiplication
+///   the added dimension is removed from the result.
+///
+/// Since a Matrix<T> can't have an empty shape, the product of two vectors is a matrix with shape
+/// [1].
+///
+/// The elements of lhs may be of another type than the ones of rhs, as long as they can be
+/// multiplied with them, and the result has the element type of rhs. This way plaintext weights
+/// can be multiplied with encrypted inputs.
+///
+/// Unlike most other operations here the matrices are taken by reference, since neither of them
+/// gets modified. The strides of lhs and rhs are respected, so transposed matrices work as well.
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, matmul};
+/// let mat1 = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+/// let mat2 = Matrix::from_iter(vec![3, 4], 1.., Layout::RowMajor);
+///
+/// let result = matmul(&mat1, &mat2).unwrap();
+///
+/// assert_eq!(result.shape(), &vec![2, 4]);
+/// assert_eq!(result.data, vec![38, 44, 50, 56, 83, 98, 113, 128]);
+/// ```
+pub fn matmul<W, T>(lhs: &Matrix<W>, rhs: &Matrix<T>) -> Result<Matrix<T>, MatrixError> where W: Clone + Default + MaybeSendSync + Mul<T, Output = T>, T: Clone + Default + MaybeSendSync + AddAssign {
+    try_matmul(
+        lhs,
+        rhs,
+        |lhs_item, rhs_item| Ok(lhs_item.clone() * rhs_item.clone()),
+        |mut sum, product| {
+            sum += product;
+            Ok(sum)
+        },
+    )
+}
+
+/// Same as matmul(), but the products and sums are computed by mul and add, which can fail. The
+/// first error is returned, as is E::from(MatrixError::MatmulShapeError) if the shapes don't fit
+/// together. This way types with checked arithmetic, e.g. ciphertexts, can be multiplied without
+/// panicking.
+///
+/// The elements of lhs and rhs may have different types than the result. The sum of every
+/// element starts from the first product, so if the contracted dimension is empty the element
+/// is T::default().
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::errors::MatrixError;
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, try_matmul};
+/// let mat1: Matrix<i32> = Matrix::from_iter(vec![2, 2], vec![1, 2, 3, 4], Layout::RowMajor);
+/// let mat2: Matrix<i32> = Matrix::from_iter(vec![2], vec![i32::MAX, 1], Layout::RowMajor);
+///
+/// let checked_mul = |lhs: &i32, rhs: &i32| lhs.checked_mul(*rhs).ok_or(MatrixError::OpError);
+/// let checked_add = |lhs: i32, rhs: i32| lhs.checked_add(rhs).ok_or(MatrixError::OpError);
+/// assert_eq!(Err(MatrixError::OpError), try_matmul(&mat1, &mat2, checked_mul, checked_add).map(|m| m.data));
+/// ```
+pub fn try_matmul<L, R, T, E, M, A>(lhs: &Matrix<L>, rhs: &Matrix<R>, mul: M, add: A) -> Result<Matrix<T>, E>
+    where
+        L: Clone + Default + MaybeSendSync,
+        R: Clone + Default + MaybeSendSync,
+        T: Clone + Default + MaybeSendSync,
+        E: From<MatrixError> + MaybeSendSync,
+        M: Fn(&L, &R) -> Result<T, E> + MaybeSendSync,
+        A: Fn(T, T) -> Result<T, E> + MaybeSendSync,
+{
+    // One-dimensional matrices get promoted to two dimensions. The added dimension has size 1, so
+    // its stride never gets used and can be anything.
+    let (lhs_shape, lhs_strides) = if lhs.shape.len() == 1 {
+        (vec![1, lhs.shape[0]], vec![0, lhs.strides[0]])
+    } else {
+        (lhs.shape.clone(), lhs.strides.clone())
+    };
+    let (rhs_shape, rhs_strides) = if rhs.shape.len() == 1 {
+        (vec![rhs.shape[0], 1], vec![rhs.strides[0], 0])
+    } else {
+        (rhs.shape.clone(), rhs.strides.clone())
     };
-    */
-    let mut new_matrix = Matrix::new(final_shape, Layout::RowMajor);
 
-    /*
-    for ((lhs_item, lhs_index), (rhs_item, rhs_index)) in lhs_iter.zip(rhs_iter){
-        assert_eq!(lhs_index, rhs_index);
-        match new_matrix.set(&lhs_index, lhs_item + rhs_item) {
-            Ok(_) => {},
-            Err(err) => {
-                return Err(err);
-            }
+    let lhs_batch_len = lhs_shape.len() - 2;
+    let rhs_batch_len = rhs_shape.len() - 2;
+    let (m, k, n) = (lhs_shape[lhs_batch_len], lhs_shape[lhs_batch_len + 1], rhs_shape[rhs_batch_len + 1]);
+    if k != rhs_shape[rhs_batch_len] {
+        return Err(MatrixError::MatmulShapeError.into());
+    }
+
+    // The strides returned by broadcast() are for contiguous matrices, so we only use the
+    // resulting shape and map the batch indices onto the real strides ourselves.
+    let batch_shape = match broadcast(&lhs_shape[..lhs_batch_len], lhs.layout, &rhs_shape[..rhs_batch_len], rhs.layout) {
+        Ok((_shape, _, _)) => _shape,
+        Err(_) => {
+            return Err(MatrixError::MatmulShapeError.into());
         }
+    };
+
+    let mut final_shape = batch_shape.clone();
+    final_shape.push(m);
+    final_shape.push(n);
+
+    // Every element of the result is computed on its own, so this can run across threads
+    let data: Result<Vec<T>, E> = collect_indexed(final_shape.iter().product(), |position| {
+        let idx = calc_idx_from_position(&batch_shape, position / (m * n));
+        let (i, j) = (position % (m * n) / n, position % n);
+        let lhs_offset = calc_batch_offset(&idx, &lhs_shape[..lhs_batch_len], &lhs_strides) + i * lhs_strides[lhs_batch_len];
+        let rhs_offset = calc_batch_offset(&idx, &rhs_shape[..rhs_batch_len], &rhs_strides) + j * rhs_strides[rhs_batch_len + 1];
+        // Same as sum_of_products(), but stops at the first error
+        let mut sum: Option<T> = None;
+        for l in 0..k {
+            let lhs_item = &lhs.data[lhs_offset + l * lhs_strides[lhs_batch_len + 1]];
+            let rhs_item = &rhs.data[rhs_offset + l * rhs_strides[rhs_batch_len]];
+            let product = mul(lhs_item, rhs_item)?;
+            sum = Some(match sum {
+                Some(sum) => add(sum, product)?,
+                None => product,
+            });
+        }
+        Ok(sum.unwrap_or_default())
+    }).into_iter().collect();
+
+    // Remove the dimensions which were added when promoting vectors
+    if rhs.shape.len() == 1 {
+        final_shape.pop();
+    }
+    if lhs.shape.len() == 1 {
+        final_shape.remove(batch_shape.len());
+    }
+    if final_shape.is_empty() {
+        final_shape.push(1);
     }
-    */
-    Ok((new_matrix, lhs, rhs))
+    Ok(Matrix::from_iter(final_shape, data?, Layout::RowMajor))
 }
-*/
-
 
+/// Calculates the physical offset of a matrix inside a stack of matrices given the broadcasted
+/// batch index. The batch dimensions of the matrix are aligned to the right of the index and
+/// dimensions of size 1 are broadcasted, i.e. always use index 0.
+fn calc_batch_offset(batch_idx: &[usize], batch_shape: &[usize], strides: &[usize]) -> usize {
+    let skipped = batch_idx.len() - batch_shape.len();
+    batch_shape
+        .iter()
+        .enumerate()
+        .filter(|(_, &dim)| dim != 1)
+        .map(|(i, _)| batch_idx[skipped + i] * strides[i])
+        .sum()
+}
 
 /// Given a two two-dimensional matrices we return the result of the multiplication of them.
-/// The API user should be careful of the fact that we change the shape and strides of the given
-/// matrices so if he needs them to be a different shape he should reshape them after the operation.
+/// This is the same as matmul(), but restricted to two-dimensional matrices.
 ///
 /// The method takes ownership of rhs and lhs for it's duration and then returns it. In the future
 /// we'll most likely add a feature to take them by reference.
@@ -913,43 +1314,14 @@ pub fn multiply<T>(mut lhs: Matrix<T>, mut rhs: Matrix<T>) -> Result<(Matrix<T>,
 /// This was added due to clippy warnings
 type MulRetType2D<T> = Result<(Matrix<T>, Matrix<T>, Matrix<T>), MatrixError>;
 
-pub fn multiply_2d<T>(mut lhs: Matrix<T>, mut rhs: Matrix<T>) -> MulRetType2D<T> where T: Display + Clone + Default + Mul + Mul<Output = T> + MulAssign + AddAssign, <T as Mul>::Output: Clone + Default{
-    let mut final_shape: Vec<usize> = Vec::new();
-
+pub fn multiply_2d<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> MulRetType2D<T> where T: Clone + Default + MaybeSendSync + Mul<Output = T> + AddAssign {
     if lhs.shape.len() != 2 || rhs.shape.len() != 2 {
         return Err(MatrixError::MatmulShapeError);
     }
-    match broadcast(lhs.shape(), lhs.layout, rhs.shape(), rhs.layout) {
-        Ok((_shape, _lhs_strides, _rhs_strides)) => {
-            lhs.set_shape(&_shape);
-            rhs.set_shape(&_shape);
-            lhs.set_strides(&_lhs_strides);
-            rhs.set_strides(&_rhs_strides);
-            final_shape = _shape;
-        },
-        Err(err) => {
-            return Err(err);
-        }
-    }
-    let mut new_matrix = Matrix::new(final_shape.clone(), Layout::RowMajor);
-    let mut curr_sum = T::default();
-    for i in 0..final_shape[0] {
-        for j in 0..final_shape[1]{
-            curr_sum = T::default();
-            for l in 0..final_shape[1]{
-                curr_sum += lhs.get_copy(&vec![i, l]).unwrap() * rhs.get_copy(&vec![l, j]).unwrap();
-            }
-
-            match new_matrix.set(&vec![i, j], curr_sum){
-                Ok(_) => {},
-                Err(err) => {
-                    return Err(err);
-                }
-            }
-
-        }
+    match matmul(&lhs, &rhs) {
+        Ok(new_matrix) => Ok((new_matrix, lhs, rhs)),
+        Err(err) => Err(err)
     }
-    Ok((new_matrix, lhs, rhs))
 }
 
 /// Given a two one-dimensional matrices(i.e. vectors) we return the result of the dot product..
@@ -967,19 +1339,182 @@ pub fn multiply_2d<T>(mut lhs: Matrix<T>, mut rhs: Matrix<T>) -> MulRetType2D<T>
 ///
 /// let (matmul, _mat1, _mat2) = multiply_1d(mat1, mat2).unwrap();
 ///
-///     println!("{}", matmul); // Should print 45
+/// assert_eq!(matmul, 45);
 /// ```
 /// This was added due to clippy warnings
 type MulRetType1D<T> = Result<(T, Matrix<T>, Matrix<T>), MatrixError>;
 pub fn multiply_1d<T>(lhs: Matrix<T>, rhs: Matrix<T>) -> MulRetType1D<T> where T: Display + Clone + Default + Mul + Mul<Output = T> + MulAssign + AddAssign, <T as Mul>::Output: Clone + Default{
-    if lhs.shape.len() != 1 || rhs.shape.len() != 1 && lhs.shape[0] == rhs.shape[0]{
+    match dot(&lhs, &rhs) {
+        Ok(curr_sum) => Ok((curr_sum, lhs, rhs)),
+        Err(err) => Err(err)
+    }
+}
+
+/// Sums the products of the given pairs. The sum starts from the first product instead of
+/// T::default(), since the default value isn't a valid zero for every type (e.g. a default
+/// CipherTextType doesn't hold a ciphertext). If there are no pairs T::default() is returned.
+fn sum_of_products<W, T, I>(pairs: I) -> T where W: Mul<T, Output = T>, T: Default + AddAssign, I: Iterator<Item = (W, T)> {
+    let mut curr_sum: Option<T> = None;
+    for (lhs_item, rhs_item) in pairs {
+        match curr_sum.as_mut() {
+            Some(sum) => *sum += lhs_item * rhs_item,
+            None => curr_sum = Some(lhs_item * rhs_item),
+        }
+    }
+    curr_sum.unwrap_or_default()
+}
+
+/// Given two one-dimensional matrices(i.e. vectors) of the same length we return their dot
+/// product. Returns MatrixError::MatmulShapeError if either of them isn't one-dimensional or their
+/// lengths differ.
+///
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, dot};
+/// let mat1 = Matrix::from_iter(vec![4], vec![1, 2, 3, 4], Layout::RowMajor);
+/// let mat2 = Matrix::from_iter(vec![4], vec![5, 6, 0, 7], Layout::RowMajor);
+///
+/// assert_eq!(dot(&mat1, &mat2).unwrap(), 45);
+/// ```
+pub fn dot<T>(lhs: &Matrix<T>, rhs: &Matrix<T>) -> Result<T, MatrixError> where T: Clone + Default + Mul<Output = T> + AddAssign {
+    if lhs.shape.len() != 1 || rhs.shape.len() != 1 || lhs.shape[0] != rhs.shape[0] {
+        return Err(MatrixError::MatmulShapeError);
+    }
+    Ok(sum_of_products((0..lhs.shape[0]).map(|i| {
+        (lhs.data[i * lhs.strides[0]].clone(), rhs.data[i * rhs.strides[0]].clone())
+    })))
+}
+
+/// Returns the outer product of lhs and rhs, i.e. result[i, j] = lhs[i] * rhs[j]. Like NumPy's
+/// outer(), matrices with more than one dimension are flattened in row major order first, so the
+/// result always has shape [lhs.size, rhs.size].
+///
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, outer};
+/// let mat1 = Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor);
+/// let mat2 = Matrix::from_iter(vec![3], vec![1, 10, 100], Layout::RowMajor);
+///
+/// let result = outer(&mat1, &mat2);
+/// assert_eq!(result.shape(), &vec![2, 3]);
+/// assert_eq!(result.data, vec![1, 10, 100, 2, 20, 200]);
+/// ```
+pub fn outer<T>(lhs: &Matrix<T>, rhs: &Matrix<T>) -> Matrix<T> where T: Clone + Default + Mul<Output = T> {
+    let lhs_data = lhs.view().to_matrix().data;
+    let rhs_data = rhs.view().to_matrix().data;
+    let mut data: Vec<T> = Vec::with_capacity(lhs_data.len() * rhs_data.len());
+    for lhs_item in &lhs_data {
+        for rhs_item in &rhs_data {
+            data.push(lhs_item.clone() * rhs_item.clone());
+        }
+    }
+    Matrix::from_iter(vec![lhs_data.len(), rhs_data.len()], data, Layout::RowMajor)
+}
+
+/// Broadcasts lhs and rhs, multiplies them element-wise and sums the products along the given
+/// axis of the broadcasted shape. This computes a batch of inner products at once, e.g. the
+/// rows of a matrix with a vector when axis is the last one.
+///
+/// Returns MatrixError::BroadcastError if the matrices aren't broadcastable and
+/// MatrixError::DimError if the axis is out of range.
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, inner};
+/// let mat1 = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+/// let mat2 = Matrix::from_iter(vec![3], vec![1, 0, 1], Layout::RowMajor);
+///
+/// assert_eq!(inner(&mat1, &mat2, 1).unwrap().data, vec![4, 10]);
+/// assert_eq!(inner(&mat1, &mat2, 0).unwrap().data, vec![5, 0, 9]);
+/// ```
+pub fn inner<T>(lhs: &Matrix<T>, rhs: &Matrix<T>, axis: usize) -> Result<Matrix<T>, MatrixError> where T: Clone + Default + Mul<Output = T> + AddAssign {
+    let products = match zip_with(lhs, rhs, |lhs_item, rhs_item| lhs_item * rhs_item) {
+        Ok(products) => products,
+        Err(err) => {
+            return Err(err);
+        }
+    };
+    products.reduce(axis, false, |mut acc, item| {
+        acc += item.clone();
+        acc
+    })
+}
+
+/// Returns the sum of the products of lhs and rhs over the given pairs of axes, the same way as
+/// NumPy's tensordot(). The axes lhs_axes[i] and rhs_axes[i] get contracted, so they must have
+/// the same size. The shape of the result consists of the remaining axes of lhs followed by the
+/// remaining axes of rhs. If no axes remain the result has shape [1].
+///
+/// Returns MatrixError::DimError if the axes are out of range, repeated or there's a different
+/// number of them for lhs and rhs and MatrixError::MatmulShapeError if the contracted axes have
+/// different sizes.
+/// # Examples
+///
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::{Matrix, tensordot};
+/// let mat1 = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+/// let mat2 = Matrix::from_iter(vec![3, 2], 1.., Layout::RowMajor);
+///
+/// // Contracting the last axis of mat1 with the first axis of mat2 is a matrix multiplication
+/// let result = tensordot(&mat1, &mat2, &[1], &[0]).unwrap();
+/// assert_eq!(result.shape(), &vec![2, 2]);
+/// assert_eq!(result.data, vec![22, 28, 49, 64]);
+///
+/// // Contracting both axes sums all of the element-wise products
+/// let result = tensordot(&mat1, &mat1, &[0, 1], &[0, 1]).unwrap();
+/// assert_eq!(result.data, vec![91]);
+/// ```
+pub fn tensordot<T>(lhs: &Matrix<T>, rhs: &Matrix<T>, lhs_axes: &[usize], rhs_axes: &[usize]) -> Result<Matrix<T>, MatrixError> where T: Clone + Default + Mul<Output = T> + AddAssign {
+    if lhs_axes.len() != rhs_axes.len() {
+        return Err(MatrixError::DimError);
+    }
+    for (axes, ndim) in [(lhs_axes, lhs.shape.len()), (rhs_axes, rhs.shape.len())] {
+        for (i, axis) in axes.iter().enumerate() {
+            if *axis >= ndim || axes[..i].contains(axis) {
+                return Err(MatrixError::DimError);
+            }
+        }
+    }
+    if lhs_axes.iter().zip(rhs_axes.iter()).any(|(l, r)| lhs.shape[*l] != rhs.shape[*r]) {
         return Err(MatrixError::MatmulShapeError);
     }
-    let mut curr_sum: T = T::default();
-    for i in 0..lhs.shape()[0] {
-        curr_sum += lhs.get_copy(&vec![i]).unwrap() * lhs.get_copy(&vec![i]).unwrap();
+
+    let lhs_free: Vec<usize> = (0..lhs.shape.len()).filter(|axis| !lhs_axes.contains(axis)).collect();
+    let rhs_free: Vec<usize> = (0..rhs.shape.len()).filter(|axis| !rhs_axes.contains(axis)).collect();
+    let free_shape: Vec<usize> = lhs_free.iter().map(|axis| lhs.shape[*axis])
+        .chain(rhs_free.iter().map(|axis| rhs.shape[*axis]))
+        .collect();
+    let contracted_shape: Vec<usize> = lhs_axes.iter().map(|axis| lhs.shape[*axis]).collect();
+
+    let mut data: Vec<T> = Vec::with_capacity(free_shape.iter().product());
+    // calc_next_idx() visits an empty shape exactly once, which is what we want for the free and
+    // the contracted axes. Axes of size 0 have no elements at all, so they're skipped.
+    let mut free_idx = if free_shape.contains(&0) { None } else { Some(vec![0; free_shape.len()]) };
+    while let Some(idx) = free_idx {
+        let (lhs_idx, rhs_idx) = idx.split_at(lhs_free.len());
+        let lhs_offset: usize = lhs_idx.iter().zip(lhs_free.iter()).map(|(i, axis)| i * lhs.strides[*axis]).sum();
+        let rhs_offset: usize = rhs_idx.iter().zip(rhs_free.iter()).map(|(i, axis)| i * rhs.strides[*axis]).sum();
+
+        let mut contracted_idx = if contracted_shape.contains(&0) { None } else { Some(vec![0; contracted_shape.len()]) };
+        let pairs = std::iter::from_fn(|| {
+            let i = contracted_idx.take()?;
+            let lhs_item = lhs.data[lhs_offset + i.iter().zip(lhs_axes.iter()).map(|(i, axis)| i * lhs.strides[*axis]).sum::<usize>()].clone();
+            let rhs_item = rhs.data[rhs_offset + i.iter().zip(rhs_axes.iter()).map(|(i, axis)| i * rhs.strides[*axis]).sum::<usize>()].clone();
+            contracted_idx = calc_next_idx(&contracted_shape, &i);
+            Some((lhs_item, rhs_item))
+        });
+        data.push(sum_of_products(pairs));
+        free_idx = calc_next_idx(&free_shape, &idx);
     }
-    Ok((curr_sum, lhs, rhs))
+
+    let final_shape = if free_shape.is_empty() { vec![1] } else { free_shape };
+    Ok(Matrix::from_iter(final_shape, data, Layout::RowMajor))
 }
 
 /*
@@ -2,3 +2,8 @@ pub mod errors;
 pub mod matrix;
 pub mod layout;
 pub mod utils;
+pub mod view;
+pub mod ops;
+pub mod serialization;
+pub mod npy;
+pub mod parallel;
@@ -0,0 +1,308 @@
+// Implements reading and writing of NumPy's .npy and .npz files, so that weights trained in
+// Python can be loaded into a Matrix<T>.
+//
+// A .npy file consists of a magic string, a version, a header which is a Python dict literal like
+// {'descr': '<i4', 'fortran_order': False, 'shape': (3, 4), } and the raw data. C ordered arrays
+// map to Layout::RowMajor and Fortran ordered ones to Layout::ColumnMajor, so the data is used
+// as is. A .npz file is a zip archive of .npy files, one for each array.
+//
+// See https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::tensor_library::errors::MatrixError;
+use crate::tensor_library::layout::Layout;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::utils::calc_strides_from_shape;
+
+const NPY_MAGIC: &[u8; 6] = b"\x93NUMPY";
+// Same limit as numpy.load(). The header length comes from the file, so it has to be checked
+// before the header gets allocated.
+const NPY_MAX_HEADER_LEN: usize = 10000;
+
+/// Element types which have a NumPy dtype. KIND and SIZE make up the dtype string, e.g. 'i' and 4
+/// give '<i4' which is NumPy's int32.
+pub trait NpyElement: Sized {
+    const KIND: char;
+    const SIZE: usize;
+
+    /// Reads an element from exactly SIZE bytes
+    fn from_npy_bytes(bytes: &[u8], little_endian: bool) -> Self;
+
+    /// Appends the little endian bytes of the element
+    fn write_npy_bytes(&self, out: &mut Vec<u8>);
+}
+
+// Implements NpyElement for the numeric types, which all have from/to_le/be_bytes.
+macro_rules! impl_npy_element {
+    ($type:ty, $kind:expr) => {
+        impl NpyElement for $type {
+            const KIND: char = $kind;
+            const SIZE: usize = std::mem::size_of::<$type>();
+
+            fn from_npy_bytes(bytes: &[u8], little_endian: bool) -> Self {
+                // The caller always passes SIZE bytes
+                let bytes = bytes.try_into().unwrap();
+                if little_endian {
+                    <$type>::from_le_bytes(bytes)
+                } else {
+                    <$type>::from_be_bytes(bytes)
+                }
+            }
+
+            fn write_npy_bytes(&self, out: &mut Vec<u8>) {
+                out.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+    };
+}
+
+impl_npy_element!(i8, 'i');
+impl_npy_element!(i16, 'i');
+impl_npy_element!(i32, 'i');
+impl_npy_element!(i64, 'i');
+impl_npy_element!(u8, 'u');
+impl_npy_element!(u16, 'u');
+impl_npy_element!(u32, 'u');
+impl_npy_element!(u64, 'u');
+impl_npy_element!(f32, 'f');
+impl_npy_element!(f64, 'f');
+
+impl NpyElement for bool {
+    const KIND: char = 'b';
+    const SIZE: usize = 1;
+
+    fn from_npy_bytes(bytes: &[u8], _little_endian: bool) -> Self {
+        bytes[0] != 0
+    }
+
+    fn write_npy_bytes(&self, out: &mut Vec<u8>) {
+        out.push(u8::from(*self));
+    }
+}
+
+/// Returns the value which follows key in the header dict, up to the next top level comma or the
+/// end of the dict.
+fn find_header_value<'a>(header: &'a str, key: &str) -> Result<&'a str, MatrixError> {
+    let key = format!("'{key}'");
+    let start = match header.find(&key) {
+        Some(pos) => pos + key.len(),
+        None => return Err(MatrixError::SerializationError),
+    };
+    let rest = header[start..].trim_start();
+    let rest = match rest.strip_prefix(':') {
+        Some(rest) => rest.trim_start(),
+        None => return Err(MatrixError::SerializationError),
+    };
+    // Tuples contain commas, so they end at the closing parenthesis
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|pos| pos + 1)
+    } else {
+        rest.find([',', '}'])
+    };
+    match end {
+        Some(end) => Ok(rest[..end].trim()),
+        None => Err(MatrixError::SerializationError),
+    }
+}
+
+/// Parses the header dict and returns the shape, layout and whether the data is little endian.
+/// Returns MatrixError::SerializationError if the dtype doesn't match T.
+fn parse_npy_header<T: NpyElement>(header: &str) -> Result<(Vec<usize>, Layout, bool), MatrixError> {
+    let descr = find_header_value(header, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let mut chars = descr.chars();
+    let little_endian = match chars.next() {
+        Some('<') | Some('|') => true,
+        Some('>') => false,
+        Some('=') => cfg!(target_endian = "little"),
+        _ => return Err(MatrixError::SerializationError),
+    };
+    if chars.next() != Some(T::KIND) || chars.as_str() != T::SIZE.to_string() {
+        return Err(MatrixError::SerializationError);
+    }
+
+    let layout = match find_header_value(header, "fortran_order")? {
+        "False" => Layout::RowMajor,
+        "True" => Layout::ColumnMajor,
+        _ => return Err(MatrixError::SerializationError),
+    };
+
+    let shape_str = find_header_value(header, "shape")?;
+    let mut shape: Vec<usize> = Vec::new();
+    for dim in shape_str.trim_start_matches('(').trim_end_matches(')').split(',') {
+        let dim = dim.trim();
+        if dim.is_empty() {
+            continue;
+        }
+        match dim.parse::<usize>() {
+            Ok(dim) => shape.push(dim),
+            Err(_) => return Err(MatrixError::SerializationError),
+        }
+    }
+    // NumPy scalars have shape (), which a matrix can't have
+    if shape.is_empty() {
+        shape.push(1);
+    }
+    Ok((shape, layout, little_endian))
+}
+
+// Implements the .npy and .npz methods
+impl<T> Matrix<T> where T: Clone + Default + NpyElement {
+    /// Reads a matrix in the .npy format. The dtype of the array must match T exactly, e.g. a
+    /// float64 array can only be read into a Matrix<f64>. Like numpy.load(), headers longer than
+    /// 10000 bytes are rejected.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::ColumnMajor);
+    ///
+    /// let bytes = mat.to_npy_bytes();
+    /// let loaded: Matrix<i32> = Matrix::read_npy(&mut bytes.as_slice()).unwrap();
+    /// assert_eq!(loaded.layout, Layout::ColumnMajor);
+    /// assert_eq!(loaded.data, mat.data);
+    /// ```
+    pub fn read_npy<R: Read>(reader: &mut R) -> Result<Matrix<T>, MatrixError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic).map_err(|_| MatrixError::SerializationError)?;
+        if &magic[..6] != NPY_MAGIC {
+            return Err(MatrixError::SerializationError);
+        }
+        // Version 1.0 has a 2 byte header length, versions 2.0 and 3.0 have 4 bytes
+        let header_len = match magic[6] {
+            1 => {
+                let mut len = [0u8; 2];
+                reader.read_exact(&mut len).map_err(|_| MatrixError::SerializationError)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0u8; 4];
+                reader.read_exact(&mut len).map_err(|_| MatrixError::SerializationError)?;
+                u32::from_le_bytes(len) as usize
+            }
+            _ => return Err(MatrixError::SerializationError),
+        };
+        if header_len > NPY_MAX_HEADER_LEN {
+            return Err(MatrixError::SerializationError);
+        }
+        let mut header = vec![0u8; header_len];
+        reader.read_exact(&mut header).map_err(|_| MatrixError::SerializationError)?;
+        let header = String::from_utf8_lossy(&header);
+        let (shape, layout, little_endian) = parse_npy_header::<T>(&header)?;
+
+        let size = match shape.iter().try_fold(1usize, |acc, dim| acc.checked_mul(*dim)) {
+            Some(size) => size,
+            None => return Err(MatrixError::SerializationError),
+        };
+        let mut bytes: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut bytes).map_err(|_| MatrixError::SerializationError)?;
+        if Some(bytes.len()) != size.checked_mul(T::SIZE) {
+            return Err(MatrixError::SerializationError);
+        }
+        let data = bytes.chunks_exact(T::SIZE).map(|chunk| T::from_npy_bytes(chunk, little_endian));
+        Ok(Matrix::from_iter(shape, data, layout))
+    }
+
+    /// Encodes the matrix in the .npy format. Column major matrices are written in Fortran order.
+    /// Matrices whose data isn't contiguous (e.g. because of set_strides()) are written in C order.
+    pub fn to_npy_bytes(&self) -> Vec<u8> {
+        let contiguous;
+        let mat = if self.strides == calc_strides_from_shape(&self.shape, self.layout) {
+            self
+        } else {
+            contiguous = self.view().to_matrix();
+            &contiguous
+        };
+
+        let shape_str = match mat.shape.len() {
+            1 => format!("({},)", mat.shape[0]),
+            _ => format!("({})", mat.shape.iter().map(|dim| dim.to_string()).collect::<Vec<String>>().join(", ")),
+        };
+        let mut header = format!(
+            "{{'descr': '{}{}{}', 'fortran_order': {}, 'shape': {}, }}",
+            if T::SIZE == 1 { '|' } else { '<' },
+            T::KIND,
+            T::SIZE,
+            if mat.layout == Layout::ColumnMajor { "True" } else { "False" },
+            shape_str,
+        );
+        // The header is padded with spaces and ends with a newline, so that the data starts at a
+        // multiple of 64 bytes
+        let unpadded_len = NPY_MAGIC.len() + 2 + 2 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded_len % 64) % 64));
+        header.push('\n');
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(unpadded_len + 64 + mat.data.len() * T::SIZE);
+        bytes.extend_from_slice(NPY_MAGIC);
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        for item in &mat.data {
+            item.write_npy_bytes(&mut bytes);
+        }
+        bytes
+    }
+
+    /// Reads a matrix from a .npy file.
+    pub fn load_npy<P: AsRef<Path>>(path: P) -> Result<Matrix<T>, MatrixError> {
+        let mut file = File::open(path).map_err(|_| MatrixError::IoError)?;
+        Matrix::read_npy(&mut file)
+    }
+
+    /// Writes the matrix to a .npy file.
+    pub fn save_npy<P: AsRef<Path>>(&self, path: P) -> Result<(), MatrixError> {
+        std::fs::write(path, self.to_npy_bytes()).map_err(|_| MatrixError::IoError)
+    }
+
+    /// Reads all arrays of a .npz archive, as written by numpy.savez() or numpy.savez_compressed().
+    /// The arrays are returned by name, i.e. the file name without the .npy extension. All arrays
+    /// must have the dtype of T.
+    pub fn read_npz<R: Read + Seek>(reader: R) -> Result<HashMap<String, Matrix<T>>, MatrixError> {
+        let mut archive = ZipArchive::new(reader).map_err(|_| MatrixError::SerializationError)?;
+        let mut arrays: HashMap<String, Matrix<T>> = HashMap::new();
+        for i in 0..archive.len() {
+            let mut file = archive.by_index(i).map_err(|_| MatrixError::SerializationError)?;
+            let name = file.name().trim_end_matches(".npy").to_string();
+            arrays.insert(name, Matrix::read_npy(&mut file)?);
+        }
+        Ok(arrays)
+    }
+
+    /// Writes the arrays to a .npz archive with the given names. Like numpy.savez() the files
+    /// aren't compressed.
+    pub fn write_npz<W: Write + Seek>(writer: W, arrays: &[(&str, &Matrix<T>)]) -> Result<(), MatrixError> {
+        let mut archive = ZipWriter::new(writer);
+        let options = FileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, mat) in arrays {
+            archive.start_file(format!("{name}.npy"), options).map_err(|_| MatrixError::IoError)?;
+            archive.write_all(&mat.to_npy_bytes()).map_err(|_| MatrixError::IoError)?;
+        }
+        archive.finish().map_err(|_| MatrixError::IoError)?;
+        Ok(())
+    }
+
+    /// Reads all arrays from a .npz file. See Matrix::read_npz().
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// // Created in Python with numpy.savez("weights.npz", w1=w1, b1=b1)
+    /// let arrays = Matrix::<f32>::load_npz("weights.npz").unwrap();
+    /// let w1 = &arrays["w1"];
+    /// ```
+    pub fn load_npz<P: AsRef<Path>>(path: P) -> Result<HashMap<String, Matrix<T>>, MatrixError> {
+        let file = File::open(path).map_err(|_| MatrixError::IoError)?;
+        Matrix::read_npz(file)
+    }
+
+    /// Writes the arrays to a .npz file. See Matrix::write_npz().
+    pub fn save_npz<P: AsRef<Path>>(path: P, arrays: &[(&str, &Matrix<T>)]) -> Result<(), MatrixError> {
+        let file = File::create(path).map_err(|_| MatrixError::IoError)?;
+        Matrix::write_npz(file, arrays)
+    }
+}
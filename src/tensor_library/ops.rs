@@ -0,0 +1,249 @@
+// Implements the std::ops traits for Matrix<T>, so that model code can be written like math:
+//
+//     let y = &(&w.matmul(&x)? + &b) * 2;
+//
+// Binary operations between matrices are element-wise and broadcast their operands with the
+// broadcast() function, the same way add() and subtract() do. Since the traits can't return a
+// Result, they panic if the shapes aren't broadcastable. Use the functions in matrix.rs if you
+// need to handle that error.
+//
+// Operations with a scalar apply the operation between every element and the scalar.
+
+use std::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use crate::tensor_library::errors::MatrixError;
+use crate::tensor_library::layout::Layout;
+use crate::tensor_library::matrix::{broadcast, matmul, Matrix};
+use crate::tensor_library::parallel::MaybeSendSync;
+use crate::tensor_library::utils::calc_next_idx;
+
+/// Returns the strides with which a matrix can be walked as if it had the broadcasted shape.
+/// Missing leading dimensions and dimensions of size 1 which got broadcasted get stride 0.
+fn calc_broadcasted_strides(shape: &[usize], strides: &[usize], broadcasted_shape: &[usize]) -> Vec<usize> {
+    let skipped = broadcasted_shape.len() - shape.len();
+    let mut result = vec![0; broadcasted_shape.len()];
+    for (i, (&dim, &stride)) in shape.iter().zip(strides.iter()).enumerate() {
+        if dim == broadcasted_shape[skipped + i] {
+            result[skipped + i] = stride;
+        }
+    }
+    result
+}
+
+/// Broadcasts lhs and rhs and returns a new row major matrix whose elements are the result of
+/// func applied to the corresponding elements of lhs and rhs. Neither matrix gets modified, so
+/// unlike add() and subtract() they are taken by reference.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// use Cryptonic::tensor_library::ops::zip_with;
+/// let lhs: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1.., Layout::RowMajor);
+/// let rhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![10, 20], Layout::RowMajor);
+///
+/// let result = zip_with(&lhs, &rhs, |a, b| a.max(b)).unwrap();
+/// assert_eq!(result.data, vec![10, 20, 10, 20]);
+/// ```
+pub fn zip_with<T, U, F>(lhs: &Matrix<T>, rhs: &Matrix<U>, mut func: F) -> Result<Matrix<T>, MatrixError>
+    where
+        T: Clone + Default,
+        U: Clone + Default,
+        F: FnMut(T, U) -> T,
+{
+    try_zip_with(lhs, rhs, |lhs, rhs| Ok(func(lhs, rhs)))
+}
+
+/// Same as zip_with(), but func can fail. The first error is returned, as is
+/// E::from(MatrixError::BroadcastError) if the matrices aren't broadcastable. This way types with
+/// checked arithmetic, e.g. ciphertexts, can be combined without panicking.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::errors::MatrixError;
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// use Cryptonic::tensor_library::ops::try_zip_with;
+/// let lhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![1, i32::MAX], Layout::RowMajor);
+/// let rhs: Matrix<i32> = Matrix::from_iter(vec![1], vec![1], Layout::RowMajor);
+///
+/// let result = try_zip_with(&lhs, &rhs, |a, b| a.checked_add(b).ok_or(MatrixError::OpError));
+/// assert_eq!(Err(MatrixError::OpError), result.map(|m| m.data));
+/// ```
+pub fn try_zip_with<T, U, E, F>(lhs: &Matrix<T>, rhs: &Matrix<U>, mut func: F) -> Result<Matrix<T>, E>
+    where
+        T: Clone + Default,
+        U: Clone + Default,
+        E: From<MatrixError>,
+        F: FnMut(T, U) -> Result<T, E>,
+{
+    let final_shape = match broadcast(lhs.shape(), lhs.layout, rhs.shape(), rhs.layout) {
+        Ok((_shape, _, _)) => _shape,
+        Err(err) => {
+            return Err(err.into());
+        }
+    };
+    let lhs_strides = calc_broadcasted_strides(lhs.shape(), lhs.strides(), &final_shape);
+    let rhs_strides = calc_broadcasted_strides(rhs.shape(), rhs.strides(), &final_shape);
+
+    let size: usize = final_shape.iter().product();
+    let mut data: Vec<T> = Vec::with_capacity(size);
+    let mut idx = if size == 0 { None } else { Some(vec![0; final_shape.len()]) };
+    while let Some(i) = idx {
+        let lhs_offset: usize = i.iter().zip(lhs_strides.iter()).map(|(i, stride)| i * stride).sum();
+        let rhs_offset: usize = i.iter().zip(rhs_strides.iter()).map(|(i, stride)| i * stride).sum();
+        data.push(func(lhs.data[lhs_offset].clone(), rhs.data[rhs_offset].clone())?);
+        idx = calc_next_idx(&final_shape, &i);
+    }
+    Ok(Matrix::from_iter(final_shape, data, Layout::RowMajor))
+}
+
+/// Same as zip_with(), but panics instead of returning an error. Used by the operator traits.
+fn zip_with_or_panic<T, F>(lhs: &Matrix<T>, rhs: &Matrix<T>, func: F) -> Matrix<T>
+    where
+        T: Clone + Default,
+        F: FnMut(T, T) -> T,
+{
+    match zip_with(lhs, rhs, func) {
+        Ok(result) => result,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Applies func to all elements of lhs and stores the results in a new matrix with the same shape
+/// and layout.
+fn map<T, F>(lhs: &Matrix<T>, func: F) -> Matrix<T>
+    where
+        T: Clone + Default,
+        F: FnMut(T) -> T,
+{
+    let mut result = Matrix::from_iter(lhs.shape().clone(), lhs.data.iter().cloned().map(func), lhs.layout);
+    // The data is copied as is, so the strides of lhs (e.g. after a transpose) still describe it
+    result.set_strides(lhs.strides());
+    result
+}
+
+/// Assigns the result of a broadcasting operation to lhs. The result must have the same shape as
+/// lhs, i.e. only rhs may get broadcasted.
+fn assign_broadcasted<T, F>(lhs: &mut Matrix<T>, rhs: &Matrix<T>, func: F)
+    where
+        T: Clone + Default,
+        F: FnMut(T, T) -> T,
+{
+    let result = zip_with_or_panic(lhs, rhs, func);
+    if result.shape() != lhs.shape() {
+        panic!("{}", MatrixError::BroadcastError);
+    }
+    *lhs = result;
+}
+
+// Implements the binary operators between matrices. Every operator is implemented for all four
+// combinations of owned and borrowed operands.
+macro_rules! impl_matrix_op {
+    ($op_trait:ident, $op_fn:ident, $assign_trait:ident, $assign_fn:ident) => {
+        impl<T> $op_trait<&Matrix<T>> for &Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: &Matrix<T>) -> Matrix<T> {
+                zip_with_or_panic(self, rhs, |a, b| a.$op_fn(b))
+            }
+        }
+
+        impl<T> $op_trait<Matrix<T>> for &Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: Matrix<T>) -> Matrix<T> {
+                self.$op_fn(&rhs)
+            }
+        }
+
+        impl<T> $op_trait<&Matrix<T>> for Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: &Matrix<T>) -> Matrix<T> {
+                (&self).$op_fn(rhs)
+            }
+        }
+
+        impl<T> $op_trait<Matrix<T>> for Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: Matrix<T>) -> Matrix<T> {
+                (&self).$op_fn(&rhs)
+            }
+        }
+
+        impl<T> $op_trait<T> for &Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: T) -> Matrix<T> {
+                map(self, |a| a.$op_fn(rhs.clone()))
+            }
+        }
+
+        impl<T> $op_trait<T> for Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            type Output = Matrix<T>;
+
+            fn $op_fn(self, rhs: T) -> Matrix<T> {
+                (&self).$op_fn(rhs)
+            }
+        }
+
+        impl<T> $assign_trait<&Matrix<T>> for Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            fn $assign_fn(&mut self, rhs: &Matrix<T>) {
+                assign_broadcasted(self, rhs, |a, b| a.$op_fn(b));
+            }
+        }
+
+        impl<T> $assign_trait<Matrix<T>> for Matrix<T> where T: Clone + Default + $op_trait<Output = T> {
+            fn $assign_fn(&mut self, rhs: Matrix<T>) {
+                assign_broadcasted(self, &rhs, |a, b| a.$op_fn(b));
+            }
+        }
+
+        impl<T> $assign_trait<T> for Matrix<T> where T: Clone + Default + MaybeSendSync + $op_trait<Output = T> {
+            fn $assign_fn(&mut self, rhs: T) {
+                self.par_apply_mut(|a| *a = a.clone().$op_fn(rhs.clone()));
+            }
+        }
+    };
+}
+
+impl_matrix_op!(Add, add, AddAssign, add_assign);
+impl_matrix_op!(Sub, sub, SubAssign, sub_assign);
+impl_matrix_op!(Mul, mul, MulAssign, mul_assign);
+
+impl<T> Neg for &Matrix<T> where T: Clone + Default + Neg<Output = T> {
+    type Output = Matrix<T>;
+
+    fn neg(self) -> Matrix<T> {
+        map(self, |a| -a)
+    }
+}
+
+impl<T> Neg for Matrix<T> where T: Clone + Default + Neg<Output = T> {
+    type Output = Matrix<T>;
+
+    fn neg(self) -> Matrix<T> {
+        -&self
+    }
+}
+
+// Rust doesn't have an operator for matrix multiplication like NumPy's @, so it's a method.
+impl<T> Matrix<T> where T: Clone + Default {
+    /// Returns the matrix product of self and rhs. Same as matmul(self, rhs).
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let w: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+    /// let x: Matrix<i32> = Matrix::from_iter(vec![3], vec![1, 0, 1], Layout::RowMajor);
+    /// let b: Matrix<i32> = Matrix::from_iter(vec![2], vec![1, 1], Layout::RowMajor);
+    ///
+    /// let y = w.matmul(&x).unwrap() + &b;
+    /// assert_eq!(y.data, vec![5, 11]);
+    /// ```
+    pub fn matmul(&self, rhs: &Matrix<T>) -> Result<Matrix<T>, MatrixError> where T: MaybeSendSync + Mul<Output = T> + AddAssign {
+        matmul(self, rhs)
+    }
+}
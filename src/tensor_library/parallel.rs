@@ -0,0 +1,51 @@
+// Implements the helpers which run the element-wise kernels and matmul across threads.
+//
+// With the `parallel` cargo feature the work is split between threads with rayon, otherwise it
+// runs on the current thread. Every element of a result is computed independently of the others
+// and the results are collected in order, so both paths give exactly the same result. This also
+// holds for CipherTextType, where a single operation can take milliseconds.
+//
+// Rayon needs the elements and the functions to be Send + Sync. Since that bound only makes sense
+// with the feature enabled, the functions here use MaybeSendSync, which is implemented for every
+// type when the feature is disabled.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Send + Sync if the `parallel` feature is enabled, otherwise implemented for every type.
+#[cfg(feature = "parallel")]
+pub trait MaybeSendSync: Send + Sync {}
+
+#[cfg(feature = "parallel")]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+
+/// Send + Sync if the `parallel` feature is enabled, otherwise implemented for every type.
+#[cfg(not(feature = "parallel"))]
+pub trait MaybeSendSync {}
+
+#[cfg(not(feature = "parallel"))]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+/// Applies func to every element of data.
+#[cfg(feature = "parallel")]
+pub(crate) fn for_each_mut<T, F>(data: &mut [T], func: F) where T: MaybeSendSync, F: Fn(&mut T) + MaybeSendSync {
+    data.par_iter_mut().for_each(func);
+}
+
+/// Applies func to every element of data.
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn for_each_mut<T, F>(data: &mut [T], func: F) where T: MaybeSendSync, F: Fn(&mut T) + MaybeSendSync {
+    data.iter_mut().for_each(func);
+}
+
+/// Returns a vector whose i-th element is func(i).
+#[cfg(feature = "parallel")]
+pub(crate) fn collect_indexed<R, F>(len: usize, func: F) -> Vec<R> where R: MaybeSendSync, F: Fn(usize) -> R + MaybeSendSync {
+    (0..len).into_par_iter().map(func).collect()
+}
+
+/// Returns a vector whose i-th element is func(i).
+#[cfg(not(feature = "parallel"))]
+pub(crate) fn collect_indexed<R, F>(len: usize, func: F) -> Vec<R> where R: MaybeSendSync, F: Fn(usize) -> R + MaybeSendSync {
+    (0..len).map(func).collect()
+}
@@ -0,0 +1,227 @@
+// Implements saving and loading of matrices.
+//
+// Matrix<T> and Layout implement serde's Serialize and Deserialize, so they can be used with any
+// serde format. Deserialized matrices are validated, so that data coming from the network can't
+// produce a matrix whose shape, strides and data disagree.
+//
+// On top of that there is a compact binary file format which is used to ship weight tensors and
+// encrypted inputs between the client and the server. It consists of a header followed by the
+// bincode encoded data:
+//
+//     magic       4 bytes     b"CRYM"
+//     version     u8          FORMAT_VERSION
+//     dtype       u8          DType::TAG of the element type
+//     layout      u8          0 = RowMajor, 1 = ColumnMajor
+//     ndim        u32 (LE)
+//     shape       ndim * u64 (LE)
+//     data        bincode encoded Vec<T>, contiguous in the given layout
+
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use crate::tensor_library::errors::MatrixError;
+use crate::tensor_library::layout::Layout;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::utils::calc_strides_from_shape;
+
+/// The first bytes of every file written by Matrix::save()
+pub const MAGIC: &[u8; 4] = b"CRYM";
+
+/// Version of the binary format. It gets incremented every time the format changes, so that old
+/// files are rejected instead of being misread.
+pub const FORMAT_VERSION: u8 = 1;
+
+/// Types which can be stored in the binary format. The tag is written to the header, so that a
+/// file can't be loaded as a matrix of a different type.
+pub trait DType {
+    const TAG: u8;
+}
+
+impl DType for bool { const TAG: u8 = 0; }
+impl DType for u8 { const TAG: u8 = 1; }
+impl DType for u16 { const TAG: u8 = 2; }
+impl DType for u32 { const TAG: u8 = 3; }
+impl DType for u64 { const TAG: u8 = 4; }
+impl DType for i8 { const TAG: u8 = 5; }
+impl DType for i16 { const TAG: u8 = 6; }
+impl DType for i32 { const TAG: u8 = 7; }
+impl DType for i64 { const TAG: u8 = 8; }
+impl DType for f32 { const TAG: u8 = 9; }
+impl DType for f64 { const TAG: u8 = 10; }
+
+/// The unvalidated form of a Matrix<T> which serde deserializes into. It gets converted to a
+/// Matrix<T> with TryFrom, which checks that all of the fields agree with each other.
+#[derive(Deserialize)]
+pub struct MatrixRepr<T> {
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    data: Vec<T>,
+    layout: Layout,
+    size: usize,
+}
+
+impl<T> TryFrom<MatrixRepr<T>> for Matrix<T> where T: Clone + Default {
+    type Error = MatrixError;
+
+    fn try_from(repr: MatrixRepr<T>) -> Result<Self, Self::Error> {
+        if repr.shape.is_empty() || repr.shape.len() != repr.strides.len() {
+            return Err(MatrixError::SerializationError);
+        }
+        // The shape could come from anywhere, so it's checked for overflows
+        let size = match repr.shape.iter().try_fold(1usize, |acc, dim| acc.checked_mul(*dim)) {
+            Some(size) => size,
+            None => return Err(MatrixError::SerializationError),
+        };
+        if size != repr.size || size != repr.data.len() {
+            return Err(MatrixError::SerializationError);
+        }
+        // The strides may be anything (e.g. after a transpose), but they must stay within data.
+        // They could come from anywhere as well, so they're checked for overflows too.
+        if size > 0 {
+            let last_idx = repr.shape.iter().zip(repr.strides.iter())
+                .try_fold(0usize, |acc, (dim, stride)| (dim - 1).checked_mul(*stride).and_then(|offset| acc.checked_add(offset)));
+            match last_idx {
+                Some(last_idx) if last_idx < repr.data.len() => {},
+                _ => return Err(MatrixError::SerializationError),
+            }
+        }
+        Ok(Matrix {
+            shape: repr.shape,
+            strides: repr.strides,
+            data: repr.data,
+            layout: repr.layout,
+            size: repr.size,
+        })
+    }
+}
+
+// Implements the serialization methods
+impl<T> Matrix<T> where T: Clone + Default {
+    /// Serializes the matrix to JSON.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1.., Layout::RowMajor);
+    ///
+    /// let json = mat.to_json().unwrap();
+    /// let loaded: Matrix<i32> = Matrix::from_json(&json).unwrap();
+    /// assert_eq!(loaded.data, vec![1, 2, 3, 4]);
+    /// ```
+    pub fn to_json(&self) -> Result<String, MatrixError> where T: Serialize {
+        serde_json::to_string(self).map_err(|_| MatrixError::SerializationError)
+    }
+
+    /// Deserializes a matrix from JSON created by self.to_json().
+    pub fn from_json(json: &str) -> Result<Matrix<T>, MatrixError> where T: DeserializeOwned {
+        serde_json::from_str(json).map_err(|_| MatrixError::SerializationError)
+    }
+
+    /// Encodes the matrix in the binary format described at the top of this file. Matrices whose
+    /// data isn't laid out contiguously (e.g. because of set_strides()) are written in row major
+    /// order.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<f64> = Matrix::from_iter(vec![2, 3], vec![0.5; 6], Layout::ColumnMajor);
+    ///
+    /// let bytes = mat.to_bytes().unwrap();
+    /// let loaded: Matrix<f64> = Matrix::from_bytes(&bytes).unwrap();
+    /// assert_eq!(loaded.shape(), &vec![2, 3]);
+    /// assert_eq!(loaded.layout, Layout::ColumnMajor);
+    ///
+    /// // The element type is part of the header
+    /// assert!(Matrix::<i32>::from_bytes(&bytes).is_err());
+    /// ```
+    pub fn to_bytes(&self) -> Result<Vec<u8>, MatrixError> where T: Serialize + DType {
+        let contiguous;
+        let mat = if self.strides == calc_strides_from_shape(&self.shape, self.layout) {
+            self
+        } else {
+            contiguous = self.view().to_matrix();
+            &contiguous
+        };
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(11 + 8 * mat.shape.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(FORMAT_VERSION);
+        bytes.push(T::TAG);
+        bytes.push(match mat.layout {
+            Layout::RowMajor => 0,
+            Layout::ColumnMajor => 1,
+        });
+        bytes.extend_from_slice(&(mat.shape.len() as u32).to_le_bytes());
+        for dim in &mat.shape {
+            bytes.extend_from_slice(&(*dim as u64).to_le_bytes());
+        }
+        match bincode::serialize_into(&mut bytes, &mat.data) {
+            Ok(_) => Ok(bytes),
+            Err(_) => Err(MatrixError::SerializationError),
+        }
+    }
+
+    /// Decodes a matrix encoded by self.to_bytes(). Returns MatrixError::SerializationError if the
+    /// header is invalid, the version or element type differ or the data doesn't match the shape.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Matrix<T>, MatrixError> where T: DeserializeOwned + DType {
+        let mut reader = ByteReader { bytes, pos: 0 };
+        if reader.take(4)? != MAGIC || reader.take(1)?[0] != FORMAT_VERSION || reader.take(1)?[0] != T::TAG {
+            return Err(MatrixError::SerializationError);
+        }
+        let layout = match reader.take(1)?[0] {
+            0 => Layout::RowMajor,
+            1 => Layout::ColumnMajor,
+            _ => return Err(MatrixError::SerializationError),
+        };
+        let ndim = u32::from_le_bytes(reader.take(4)?.try_into().unwrap()) as usize;
+        let mut shape: Vec<usize> = Vec::with_capacity(ndim.min(64));
+        for _ in 0..ndim {
+            let dim = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+            shape.push(usize::try_from(dim).map_err(|_| MatrixError::SerializationError)?);
+        }
+
+        let data: Vec<T> = match bincode::deserialize(&bytes[reader.pos..]) {
+            Ok(data) => data,
+            Err(_) => return Err(MatrixError::SerializationError),
+        };
+        Matrix::try_from(MatrixRepr {
+            strides: calc_strides_from_shape(&shape, layout),
+            size: data.len(),
+            shape,
+            data,
+            layout,
+        })
+    }
+
+    /// Writes the matrix to a file in the binary format.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), MatrixError> where T: Serialize + DType {
+        let bytes = self.to_bytes()?;
+        fs::write(path, bytes).map_err(|_| MatrixError::IoError)
+    }
+
+    /// Reads a matrix from a file written by self.save().
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Matrix<T>, MatrixError> where T: DeserializeOwned + DType {
+        let bytes = fs::read(path).map_err(|_| MatrixError::IoError)?;
+        Matrix::from_bytes(&bytes)
+    }
+}
+
+/// Reads the header of the binary format
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], MatrixError> {
+        if self.bytes.len() - self.pos < len {
+            return Err(MatrixError::SerializationError);
+        }
+        self.pos += len;
+        Ok(&self.bytes[self.pos - len..self.pos])
+    }
+}
@@ -5,13 +5,13 @@ use super::layout::Layout;
 ///
 /// # Examples
 /// ```
-/// use Cryptonic::{matrix::Matrix, layout::Layout};
-/// use Cryptonic::utils::calc_strides_from_shape;
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::utils::calc_strides_from_shape;
 /// let mut val = calc_strides_from_shape(&vec![3, 4], Layout::RowMajor);
 /// println!("{:?}", val); // Prints [4, 1]
 /// ```
 /// TODO: Add tests
-pub fn calc_strides_from_shape(shape: &Vec<usize>, layout: Layout) -> Vec<usize> {
+pub fn calc_strides_from_shape(shape: &[usize], layout: Layout) -> Vec<usize> {
     let mut data_size: usize = 1;
     let mut strides: Vec<usize> = vec![0; shape.len()];
 
@@ -35,7 +35,7 @@ pub fn calc_strides_from_shape(shape: &Vec<usize>, layout: Layout) -> Vec<usize>
 ///
 /// # Examples
 /// ```
-/// use Cryptonic::utils::calc_size_from_shape;
+/// use Cryptonic::tensor_library::utils::calc_size_from_shape;
 /// let mut val = calc_size_from_shape(&vec![3, 4]);
 /// println!("{}", val); // Prints 12, because 12 = 3*4
 /// ```
@@ -49,14 +49,14 @@ pub fn calc_size_from_shape(shape: &[usize]) -> usize {
 ///
 /// # Examples
 /// ```
-/// use Cryptonic::utils::calc_size_from_shape;
-/// use Cryptonic::utils::check_concat_dims;
+/// use Cryptonic::tensor_library::utils::calc_size_from_shape;
+/// use Cryptonic::tensor_library::utils::check_concat_dims;
 /// let vec_1 = vec![3, 4, 5];
 /// let vec_2 = vec![3, 2, 5];
 /// let mut val = check_concat_dims(&vec_1, &vec_2, 1);
 /// println!("{:?}", val); // Prints true
 /// ```
-pub fn check_concat_dims(lhs: &Vec<usize>, rhs: &Vec<usize>, axis: usize) -> bool{
+pub fn check_concat_dims(lhs: &[usize], rhs: &[usize], axis: usize) -> bool{
     if lhs.len() != rhs.len() {
         return false;
     }
@@ -79,18 +79,57 @@ pub fn check_concat_dims(lhs: &Vec<usize>, rhs: &Vec<usize>, axis: usize) -> boo
 ///
 /// # Examples
 /// ```
-/// use Cryptonic::utils::calc_size_from_shape;
-/// use Cryptonic::utils::calc_concat_shape;
+/// use Cryptonic::tensor_library::utils::calc_size_from_shape;
+/// use Cryptonic::tensor_library::utils::calc_concat_shape;
 /// let vec_1 = vec![3, 4, 5];
 /// let vec_2 = vec![3, 2, 5];
 /// let mut val = calc_concat_shape(&vec_1, &vec_2, 1);
 /// println!("{:?}", val); // Prints [3, 6, 5]
 /// ```
-pub fn calc_concat_shape(lhs: &Vec<usize>, rhs: &Vec<usize>, axis: usize) -> Option<Vec<usize>>{
+pub fn calc_concat_shape(lhs: &[usize], rhs: &[usize], axis: usize) -> Option<Vec<usize>>{
     if !check_concat_dims(lhs, rhs, axis) {
         return None
     }
-    let mut f_vec = lhs.clone();
+    let mut f_vec = lhs.to_vec();
     f_vec[axis] += rhs[axis];
     Some(f_vec)
-}
\ No newline at end of file
+}
+
+/// Calculates the index which comes after idx when iterating over a matrix with the given shape
+/// in row major order. Returns None if idx is the last index.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::utils::calc_next_idx;
+/// assert_eq!(calc_next_idx(&vec![3, 3], &vec![1, 2]), Some(vec![2, 0]));
+/// assert_eq!(calc_next_idx(&vec![3, 3], &vec![2, 2]), None);
+/// ```
+pub fn calc_next_idx(shape: &[usize], idx: &[usize]) -> Option<Vec<usize>> {
+    let mut next_idx = idx.to_vec();
+    for i in (0..shape.len()).rev() {
+        if next_idx[i] + 1 < shape[i] {
+            next_idx[i] += 1;
+            return Some(next_idx);
+        }
+        next_idx[i] = 0;
+    }
+    None
+}
+
+/// Calculates the index of the element at the given position when iterating over a matrix with
+/// the given shape in row major order.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::utils::calc_idx_from_position;
+/// assert_eq!(calc_idx_from_position(&vec![3, 4], 6), vec![1, 2]);
+/// assert_eq!(calc_idx_from_position(&vec![2, 3, 4], 23), vec![1, 2, 3]);
+/// ```
+pub fn calc_idx_from_position(shape: &[usize], mut position: usize) -> Vec<usize> {
+    let mut idx = vec![0; shape.len()];
+    for i in (0..shape.len()).rev() {
+        idx[i] = position % shape[i];
+        position /= shape[i];
+    }
+    idx
+}
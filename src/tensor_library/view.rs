@@ -0,0 +1,400 @@
+use crate::tensor_library::errors::MatrixError;
+use crate::tensor_library::layout::Layout;
+use crate::tensor_library::matrix::Matrix;
+use crate::tensor_library::utils::calc_next_idx;
+
+/// Describes how a single dimension of a matrix gets sliced. A slice is given as a list of these,
+/// one for each dimension of the matrix except for NewAxis, which doesn't consume a dimension.
+/// If fewer elements than dimensions are given, the remaining dimensions are taken whole.
+///
+/// - Range selects the elements start, start + step, ... up to (not including) end. If end is
+///   None, the range goes until the end of the dimension.
+/// - Index selects a single element and removes the dimension from the view.
+/// - NewAxis inserts a new dimension of size 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slice {
+    Range {
+        start: usize,
+        end: Option<usize>,
+        step: usize,
+    },
+    Index(usize),
+    NewAxis,
+}
+
+impl Slice {
+    /// Selects the whole dimension
+    pub fn full() -> Slice {
+        Slice::Range { start: 0, end: None, step: 1 }
+    }
+
+    /// Selects the elements in [start, end)
+    pub fn range(start: usize, end: usize) -> Slice {
+        Slice::Range { start, end: Some(end), step: 1 }
+    }
+
+    /// Selects every step-th element in [start, end)
+    pub fn step(start: usize, end: usize, step: usize) -> Slice {
+        Slice::Range { start, end: Some(end), step }
+    }
+}
+
+/// Calculates the shape, strides and offset of a slice from the shape, strides and offset of the
+/// sliced matrix. Nothing is copied, the result describes a different walk over the same data.
+///
+/// Returns MatrixError::SliceError if there are more slice elements than dimensions, if an index
+/// or range is out of bounds, if a range is empty or if a step is 0.
+fn calc_slice_layout(
+    shape: &[usize],
+    strides: &[usize],
+    offset: usize,
+    info: &[Slice],
+) -> Result<(Vec<usize>, Vec<usize>, usize), MatrixError> {
+    let consumed = info.iter().filter(|s| **s != Slice::NewAxis).count();
+    if consumed > shape.len() {
+        return Err(MatrixError::SliceError);
+    }
+
+    let mut new_shape: Vec<usize> = Vec::with_capacity(shape.len() + info.len() - consumed);
+    let mut new_strides: Vec<usize> = Vec::with_capacity(shape.len() + info.len() - consumed);
+    let mut new_offset = offset;
+    let mut dim = 0;
+
+    for slice in info.iter().copied().chain(vec![Slice::full(); shape.len() - consumed]) {
+        match slice {
+            Slice::Range { start, end, step } => {
+                let end = end.unwrap_or(shape[dim]);
+                if step == 0 || start >= end || end > shape[dim] {
+                    return Err(MatrixError::SliceError);
+                }
+                new_offset += start * strides[dim];
+                new_shape.push((end - start).div_ceil(step));
+                new_strides.push(strides[dim] * step);
+                dim += 1;
+            }
+            Slice::Index(idx) => {
+                if idx >= shape[dim] {
+                    return Err(MatrixError::SliceError);
+                }
+                new_offset += idx * strides[dim];
+                dim += 1;
+            }
+            Slice::NewAxis => {
+                new_shape.push(1);
+                new_strides.push(0);
+            }
+        }
+    }
+
+    // A Matrix can't have an empty shape, so indexing all dimensions results in shape [1]
+    if new_shape.is_empty() {
+        new_shape.push(1);
+        new_strides.push(0);
+    }
+    Ok((new_shape, new_strides, new_offset))
+}
+
+/// Checks that a walk with the given shape, strides and offset stays within a buffer of the given
+/// length. Used for views created from arbitrary strides.
+fn check_view_layout(shape: &[usize], strides: &[usize], offset: usize, data_len: usize) -> Result<(), MatrixError> {
+    if shape.is_empty() || shape.len() != strides.len() || shape.contains(&0) {
+        return Err(MatrixError::ViewError);
+    }
+    let last_idx: usize = offset + shape.iter().zip(strides.iter()).map(|(dim, stride)| (dim - 1) * stride).sum::<usize>();
+    if last_idx >= data_len {
+        return Err(MatrixError::ViewError);
+    }
+    Ok(())
+}
+
+/// A borrowed, read-only view into the data of a Matrix<T>. A view has its own shape, strides and
+/// offset, so slicing, striding and adding axes doesn't copy any of the underlying data.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// use Cryptonic::tensor_library::view::Slice;
+/// let mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 0.., Layout::RowMajor);
+///
+/// // Every second column of the last two rows
+/// let view = mat.slice(&[Slice::range(1, 3), Slice::step(0, 4, 2)]).unwrap();
+/// assert_eq!(view.shape(), &vec![2, 2]);
+/// assert_eq!(view.get(&vec![1, 1]).unwrap(), &10);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MatrixView<'a, T> where T: Clone + Default + 'static {
+    data: &'a [T],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+/// The mutable counterpart of MatrixView<'a, T>. Writes through the view change the data of the
+/// matrix it was created from.
+///
+/// # Examples
+/// ```
+/// use Cryptonic::tensor_library::layout::Layout;
+/// use Cryptonic::tensor_library::matrix::Matrix;
+/// use Cryptonic::tensor_library::view::Slice;
+/// let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 0.., Layout::RowMajor);
+///
+/// // Zero the second column
+/// let mut view = mat.slice_mut(&[Slice::full(), Slice::Index(1)]).unwrap();
+/// view.apply_mut(|n| *n = 0);
+/// assert_eq!(mat.data, vec![0, 0, 2, 3, 0, 5]);
+/// ```
+#[derive(Debug)]
+pub struct MatrixViewMut<'a, T> where T: Clone + Default + 'static {
+    data: &'a mut [T],
+    shape: Vec<usize>,
+    strides: Vec<usize>,
+    offset: usize,
+}
+
+// Implements the methods of Matrix<T> which create views.
+impl<T> Matrix<T> where T: Clone + Default {
+    /// Returns a view over the whole matrix.
+    pub fn view(&self) -> MatrixView<'_, T> {
+        MatrixView {
+            data: &self.data,
+            shape: self.shape.clone(),
+            strides: self.strides.clone(),
+            offset: 0,
+        }
+    }
+
+    /// Returns a mutable view over the whole matrix.
+    pub fn view_mut(&mut self) -> MatrixViewMut<'_, T> {
+        MatrixViewMut {
+            shape: self.shape.clone(),
+            strides: self.strides.clone(),
+            data: &mut self.data,
+            offset: 0,
+        }
+    }
+
+    /// Returns a view of the part of the matrix selected by info. See Slice for how each
+    /// dimension can be sliced.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::errors::MatrixError;
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// use Cryptonic::tensor_library::view::Slice;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 0.., Layout::RowMajor);
+    ///
+    /// let row = mat.slice(&[Slice::Index(2)]).unwrap();
+    /// assert_eq!(row.shape(), &vec![4]);
+    /// assert_eq!(row.to_matrix().data, vec![8, 9, 10, 11]);
+    ///
+    /// assert_eq!(Err(MatrixError::SliceError), mat.slice(&[Slice::Index(3)]).map(|v| v.to_matrix().data));
+    /// ```
+    pub fn slice(&self, info: &[Slice]) -> Result<MatrixView<'_, T>, MatrixError> {
+        let (shape, strides, offset) = calc_slice_layout(&self.shape, &self.strides, 0, info)?;
+        Ok(MatrixView { data: &self.data, shape, strides, offset })
+    }
+
+    /// Same as self.slice(), but returns a mutable view.
+    pub fn slice_mut(&mut self, info: &[Slice]) -> Result<MatrixViewMut<'_, T>, MatrixError> {
+        let (shape, strides, offset) = calc_slice_layout(&self.shape, &self.strides, 0, info)?;
+        Ok(MatrixViewMut { data: &mut self.data, shape, strides, offset })
+    }
+
+    /// Returns a view with arbitrary shape and strides starting at offset in self.data. This
+    /// should be used with caution, since any element can be reached in many ways, for example
+    /// setting a stride to 0 repeats the data along that dimension.
+    ///
+    /// Returns MatrixError::ViewError if the view would reach outside of the data.
+    ///
+    /// # Examples
+    /// ```
+    /// use Cryptonic::tensor_library::layout::Layout;
+    /// use Cryptonic::tensor_library::matrix::Matrix;
+    /// let mat: Matrix<i32> = Matrix::from_iter(vec![3], 1.., Layout::RowMajor);
+    ///
+    /// // Repeats the vector as the rows of a 2x3 matrix
+    /// let view = mat.as_strided(&[2, 3], &[0, 1], 0).unwrap();
+    /// assert_eq!(view.to_matrix().data, vec![1, 2, 3, 1, 2, 3]);
+    /// ```
+    pub fn as_strided(&self, shape: &[usize], strides: &[usize], offset: usize) -> Result<MatrixView<'_, T>, MatrixError> {
+        check_view_layout(shape, strides, offset, self.data.len())?;
+        Ok(MatrixView {
+            data: &self.data,
+            shape: shape.to_vec(),
+            strides: strides.to_vec(),
+            offset,
+        })
+    }
+}
+
+impl<'a, T> MatrixView<'a, T> where T: Clone + Default {
+    /// Returns the shape of the view
+    pub fn shape(&self) -> &Vec<usize> {
+        &self.shape
+    }
+
+    /// Returns the strides of the view. These are in terms of the data of the viewed matrix.
+    pub fn strides(&self) -> &Vec<usize> {
+        &self.strides
+    }
+
+    /// Returns the number of elements in the view
+    pub fn size(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Same as Matrix::check_bounds()
+    pub fn check_bounds(&self, idx: &[usize]) -> Result<bool, MatrixError> {
+        if idx.len() != self.shape.len() {
+            return Err(MatrixError::DimError);
+        }
+        match !idx.iter().zip(self.shape.iter()).any(|(x, y)| x >= y) {
+            true => Ok(true),
+            false => Err(MatrixError::OutOfBounds),
+        }
+    }
+
+    /// Returns the physical id of idx in the data of the viewed matrix
+    pub fn get_physical_idx(&self, idx: &[usize]) -> Result<usize, MatrixError> {
+        self.check_bounds(idx)?;
+        Ok(self.offset + idx.iter().zip(self.strides.iter()).map(|(i, stride)| i * stride).sum::<usize>())
+    }
+
+    /// Returns a reference to the element at idx. The reference lives as long as the matrix,
+    /// not just as long as the view.
+    pub fn get(&self, idx: &[usize]) -> Result<&'a T, MatrixError> {
+        let data: &'a [T] = self.data;
+        match self.get_physical_idx(idx) {
+            Ok(physical_idx) => Ok(&data[physical_idx]),
+            Err(m_err) => Err(m_err),
+        }
+    }
+
+    /// Same as self.get(), but returns a copy
+    pub fn get_copy(&self, idx: &[usize]) -> Result<T, MatrixError> {
+        self.get(idx).cloned()
+    }
+
+    /// Slices the view further. The result borrows the data of the original matrix.
+    pub fn slice(&self, info: &[Slice]) -> Result<MatrixView<'a, T>, MatrixError> {
+        let (shape, strides, offset) = calc_slice_layout(&self.shape, &self.strides, self.offset, info)?;
+        Ok(MatrixView { data: self.data, shape, strides, offset })
+    }
+
+    /// Apply a function to all elements of the view in row major order.
+    pub fn apply<F: FnMut(&T)>(&self, mut func: F) {
+        let mut idx = if self.size() == 0 { None } else { Some(vec![0; self.shape.len()]) };
+        while let Some(i) = idx {
+            // i is always within the shape of the view, so this can't fail
+            func(self.get(&i).unwrap());
+            idx = calc_next_idx(&self.shape, &i);
+        }
+    }
+
+    /// Copies the elements of the view into a new row major matrix. This is the only place where
+    /// the data gets cloned.
+    pub fn to_matrix(&self) -> Matrix<T> {
+        let mut data: Vec<T> = Vec::with_capacity(self.size());
+        self.apply(|item| data.push(item.clone()));
+        Matrix::from_iter(self.shape.clone(), data, Layout::RowMajor)
+    }
+}
+
+impl<'a, T> MatrixViewMut<'a, T> where T: Clone + Default {
+    /// Returns the shape of the view
+    pub fn shape(&self) -> &Vec<usize> {
+        &self.shape
+    }
+
+    /// Returns the strides of the view. These are in terms of the data of the viewed matrix.
+    pub fn strides(&self) -> &Vec<usize> {
+        &self.strides
+    }
+
+    /// Same as MatrixView::size()
+    pub fn size(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Returns a read-only view of the same elements.
+    pub fn view(&self) -> MatrixView<'_, T> {
+        MatrixView {
+            data: self.data,
+            shape: self.shape.clone(),
+            strides: self.strides.clone(),
+            offset: self.offset,
+        }
+    }
+
+    /// Same as MatrixView::get_physical_idx()
+    pub fn get_physical_idx(&self, idx: &[usize]) -> Result<usize, MatrixError> {
+        self.view().get_physical_idx(idx)
+    }
+
+    /// Same as MatrixView::get()
+    pub fn get(&self, idx: &[usize]) -> Result<&T, MatrixError> {
+        match self.get_physical_idx(idx) {
+            Ok(physical_idx) => Ok(&self.data[physical_idx]),
+            Err(m_err) => Err(m_err),
+        }
+    }
+
+    /// Same as self.get(), but returns a mutable reference
+    pub fn get_mut(&mut self, idx: &[usize]) -> Result<&mut T, MatrixError> {
+        match self.get_physical_idx(idx) {
+            Ok(physical_idx) => Ok(&mut self.data[physical_idx]),
+            Err(m_err) => Err(m_err),
+        }
+    }
+
+    /// Sets the element at idx to value
+    pub fn set(&mut self, idx: &[usize], value: T) -> Result<(), MatrixError> {
+        match self.get_mut(idx) {
+            Ok(cell) => {
+                *cell = value;
+                Ok(())
+            }
+            Err(m_err) => Err(m_err),
+        }
+    }
+
+    /// Slices the view further. The result mutably borrows this view for its lifetime.
+    pub fn slice_mut(&mut self, info: &[Slice]) -> Result<MatrixViewMut<'_, T>, MatrixError> {
+        let (shape, strides, offset) = calc_slice_layout(&self.shape, &self.strides, self.offset, info)?;
+        Ok(MatrixViewMut { data: self.data, shape, strides, offset })
+    }
+
+    /// Apply a function to all elements of the view in row major order. The elements are given as
+    /// mutable references and can therefore be modified.
+    ///
+    /// If the view was created with a stride of 0 the same element will be visited more than once.
+    pub fn apply_mut<F: FnMut(&mut T)>(&mut self, mut func: F) {
+        let mut idx = if self.size() == 0 { None } else { Some(vec![0; self.shape.len()]) };
+        while let Some(i) = idx {
+            // i is always within the shape of the view, so this can't fail
+            func(self.get_mut(&i).unwrap());
+            idx = calc_next_idx(&self.shape, &i);
+        }
+    }
+
+    /// Sets all elements of the view to value
+    pub fn fill(&mut self, value: T) {
+        self.apply_mut(|item| *item = value.clone());
+    }
+
+    /// Copies the elements of src into the view. The shapes must be the same.
+    pub fn assign(&mut self, src: &MatrixView<T>) -> Result<(), MatrixError> {
+        if src.shape() != self.shape() {
+            return Err(MatrixError::ShapeError);
+        }
+        let mut idx = if self.size() == 0 { None } else { Some(vec![0; self.shape.len()]) };
+        while let Some(i) = idx {
+            self.set(&i, src.get_copy(&i)?)?;
+            idx = calc_next_idx(&self.shape, &i);
+        }
+        Ok(())
+    }
+}
@@ -5,8 +5,10 @@ mod test_matrix_functionality {
     
     use Cryptonic::tensor_library::errors::MatrixError;
     use Cryptonic::tensor_library::layout::Layout;
-    use Cryptonic::tensor_library::matrix::{add, broadcast, concat, Matrix, MatrixIter, multiply_2d, subtract};
-    use Cryptonic::tensor_library::utils::check_concat_dims;
+    use Cryptonic::tensor_library::matrix::{add, broadcast, concat, divide, dot, inner, matmul, Matrix, MatrixIter, multiply, multiply_1d, multiply_2d, outer, remainder, subtract, tensordot, try_matmul};
+    use Cryptonic::tensor_library::ops::try_zip_with;
+    use Cryptonic::tensor_library::utils::{calc_next_idx, check_concat_dims};
+    use Cryptonic::tensor_library::view::Slice;
 
     #[test]
     fn test_row_major_gen() {
@@ -62,8 +64,8 @@ mod test_matrix_functionality {
     fn test_reshape() {
         let mut mat: Matrix<i32> = Matrix::new(vec![100], Layout::RowMajor);
 
-        assert_eq!(Err(MatrixError::ReshapeError), mat.reshape(&vec![20, 6]));
-        let _l = mat.reshape(&vec![20, 5]);
+        assert_eq!(Err(MatrixError::ReshapeError), mat.reshape(&[20, 6]));
+        let _l = mat.reshape(&[20, 5]);
         assert_eq!(mat.shape(), &vec![20, 5]);
     }
 
@@ -73,15 +75,15 @@ mod test_matrix_functionality {
         let mat_2: Matrix<i32> = Matrix::new(vec![10, 20, 30], Layout::ColumnMajor);
         assert_eq!(
             Err(MatrixError::OutOfBounds),
-            mat_1.check_bounds(&vec![3, 4])
+            mat_1.check_bounds(&[3, 4])
         );
-        assert!(mat_1.check_bounds(&vec![2, 3]).unwrap());
+        assert!(mat_1.check_bounds(&[2, 3]).unwrap());
 
         assert_eq!(
             Err(MatrixError::OutOfBounds),
-            mat_2.check_bounds(&vec![3, 4, 83])
+            mat_2.check_bounds(&[3, 4, 83])
         );
-        assert!(mat_2.check_bounds(&vec![2, 3, 2]).unwrap());
+        assert!(mat_2.check_bounds(&[2, 3, 2]).unwrap());
     }
 
     #[test]
@@ -105,7 +107,7 @@ mod test_matrix_functionality {
         let (x, y, z) = (4, 0, 0);
         // Expect error  to be thrown if index out of bounds
         let expected_error = Err(MatrixError::OutOfBounds);
-        assert_eq!(expected_error, mat.get_physical_idx(&vec![x, y, z]));
+        assert_eq!(expected_error, mat.get_physical_idx(&[x, y, z]));
 
         // The matrix [4, 3, 7] has stride (21, 7, 1)
         // Expect physical id of element [1, 2, 3] to equal 1*21 + 2*7 + 3*1
@@ -113,14 +115,14 @@ mod test_matrix_functionality {
         let (x, y, z) = (1, 2, 3);
         assert_eq!(
             Ok(21 + 2 * 7 + 3),
-            mat.get_physical_idx(&vec![x, y, z])
+            mat.get_physical_idx(&[x, y, z])
         );
     }
 
     // TODO: Add more matrix_tests
     #[test]
     fn test_broadcasting() {
-        match broadcast(&vec![3], Layout::ColumnMajor, &vec![3, 1], Layout::RowMajor) {
+        match broadcast(&[3], Layout::ColumnMajor, &[3, 1], Layout::RowMajor) {
             Ok((v1, v2, v3)) => {
                 println!("{v1:?}");
                 println!("{v2:?}");
@@ -135,8 +137,8 @@ mod test_matrix_functionality {
         let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 1.., Layout::RowMajor);
         mat.apply_mut(|n| *n *= 2);
 
-        assert_eq!(Ok(&14), mat.get(&vec![1, 2]));
-        assert_eq!(Err(MatrixError::OutOfBounds), mat.get(&vec![3, 4]));
+        assert_eq!(Ok(&14), mat.get(&[1, 2]));
+        assert_eq!(Err(MatrixError::OutOfBounds), mat.get(&[3, 4]));
     }
 
     #[test]
@@ -144,26 +146,26 @@ mod test_matrix_functionality {
         let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 1.., Layout::RowMajor);
         mat.apply_mut(|n| *n *= 2);
 
-        let x = match mat.get_mut(&vec![0, 0]) {
+        let x = match mat.get_mut(&[0, 0]) {
             Ok(val) => val,
             Err(_) => panic!(),
         };
         *x = 5;
 
-        assert_eq!(Ok(&5), mat.get(&vec![0, 0]));
+        assert_eq!(Ok(&5), mat.get(&[0, 0]));
     }
 
     #[test]
     fn test_set() {
         let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 1.., Layout::RowMajor);
 
-        assert_eq!(Ok(()), mat.set(&vec![0, 0], 5));
-        assert_eq!(Ok(()), mat.set(&vec![0, 1], 2));
-        assert_eq!(Ok(()), mat.set(&vec![0, 2], 8));
+        assert_eq!(Ok(()), mat.set(&[0, 0], 5));
+        assert_eq!(Ok(()), mat.set(&[0, 1], 2));
+        assert_eq!(Ok(()), mat.set(&[0, 2], 8));
         // Index out of bounds, since 7 > 4-1
-        assert_eq!(Err(MatrixError::OutOfBounds), mat.set(&vec![0, 7], 8));
+        assert_eq!(Err(MatrixError::OutOfBounds), mat.set(&[0, 7], 8));
         // Number of dims doesn't match
-        assert_eq!(Err(MatrixError::DimError), mat.set(&vec![0, 2, 1], 8));
+        assert_eq!(Err(MatrixError::DimError), mat.set(&[0, 2, 1], 8));
     }
 
     #[test]
@@ -181,6 +183,17 @@ mod test_matrix_functionality {
         mat.apply_mut(|n| *n *= 2);
 
         assert_eq!(mat.data, vec![2, 4, 6, 8]);
+
+        // The function may have mutable state
+        let mut count = 0;
+        mat.apply_mut(|n| {
+            count += 1;
+            *n += count;
+        });
+        assert_eq!(mat.data, vec![3, 6, 9, 12]);
+
+        mat.par_apply_mut(|n| *n -= 1);
+        assert_eq!(mat.data, vec![2, 5, 8, 11]);
     }
 
     #[test]
@@ -190,17 +203,99 @@ mod test_matrix_functionality {
 
         mat.transpose();
 
-        assert_eq!(mat.get(&vec![0, 0]).unwrap(), &2);
-        assert_eq!(mat.get(&vec![0, 1]).unwrap(), &8);
-        assert_eq!(mat.get(&vec![1, 0]).unwrap(), &4);
-        assert_eq!(mat.get(&vec![1, 1]).unwrap(), &10);
-        assert_eq!(mat.get(&vec![2, 0]).unwrap(), &6);
-        assert_eq!(mat.get(&vec![2, 1]).unwrap(), &12);
+        assert_eq!(mat.get(&[0, 0]).unwrap(), &2);
+        assert_eq!(mat.get(&[0, 1]).unwrap(), &8);
+        assert_eq!(mat.get(&[1, 0]).unwrap(), &4);
+        assert_eq!(mat.get(&[1, 1]).unwrap(), &10);
+        assert_eq!(mat.get(&[2, 0]).unwrap(), &6);
+        assert_eq!(mat.get(&[2, 1]).unwrap(), &12);
+    }
+
+    #[test]
+    fn test_permute() {
+        let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3, 4], 0.., Layout::RowMajor);
+        let original = mat.clone();
+        mat.permute(&[1, 2, 0]).unwrap();
+        assert_eq!(mat.shape(), &vec![3, 4, 2]);
+        for i in 0..2 {
+            for j in 0..3 {
+                for k in 0..4 {
+                    assert_eq!(mat.get(&[j, k, i]), original.get(&[i, j, k]));
+                }
+            }
+        }
+
+        mat.swap_axes(0, 1).unwrap();
+        assert_eq!(mat.shape(), &vec![4, 3, 2]);
+        assert_eq!(mat.get(&[3, 2, 1]), original.get(&[1, 2, 3]));
+
+        assert_eq!(mat.permute(&[0, 1]), Err(MatrixError::DimError));
+        assert_eq!(mat.permute(&[0, 1, 1]), Err(MatrixError::DimError));
+        assert_eq!(mat.swap_axes(0, 3), Err(MatrixError::DimError));
+    }
+
+    #[test]
+    fn test_contiguity() {
+        let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        mat.transpose();
+        assert!(!mat.is_contiguous());
+
+        let row_major = mat.to_contiguous(Layout::RowMajor);
+        assert!(row_major.is_contiguous());
+        assert_eq!(row_major.data, vec![1, 4, 2, 5, 3, 6]);
+        let column_major = mat.to_contiguous(Layout::ColumnMajor);
+        assert!(column_major.is_contiguous());
+        assert_eq!(column_major.data, vec![1, 2, 3, 4, 5, 6]);
+
+        // Reshaping a transposed matrix keeps the transposed order of the elements
+        let mut reshaped = mat.clone();
+        reshaped.reshape(&[2, 3]).unwrap();
+        assert_eq!(reshaped.get(&[0, 2]), Ok(&2));
+        assert_eq!(reshaped.get(&[1, 0]), Ok(&5));
+        mat.flatten();
+        assert_eq!(mat.data, vec![1, 4, 2, 5, 3, 6]);
+
+        // add() broadcasts transposed matrices correctly as well
+        let mut lhs: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        lhs.transpose();
+        let rhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![10, 20], Layout::RowMajor);
+        let (result, _lhs, _rhs) = add(lhs, rhs).unwrap();
+        assert_eq!(result.data, vec![11, 24, 12, 25, 13, 26]);
+    }
+
+    #[test]
+    fn test_kernels_match_serial_results() {
+        // Run with and without the parallel feature, both must give exactly these results
+        let lhs: Matrix<i64> = Matrix::from_iter(vec![3, 16, 32], (0..).map(|n| n % 7 - 3), Layout::RowMajor);
+        let rhs: Matrix<i64> = Matrix::from_iter(vec![32, 8], (0..).map(|n| n % 5 - 2), Layout::RowMajor);
+
+        let result = matmul(&lhs, &rhs).unwrap();
+        assert_eq!(result.shape(), &vec![3, 16, 8]);
+        for b in 0..3 {
+            for i in 0..16 {
+                for j in 0..8 {
+                    let expected: i64 = (0..32).map(|k| lhs.get_copy(&[b, i, k]).unwrap() * rhs.get_copy(&[k, j]).unwrap()).sum();
+                    assert_eq!(result.get_copy(&[b, i, j]).unwrap(), expected);
+                }
+            }
+        }
+
+        let bias: Matrix<i64> = Matrix::from_iter(vec![8], 0.., Layout::RowMajor);
+        let (sum, result, _bias) = add(result, bias).unwrap();
+        let (difference, _, _) = subtract(sum.clone(), result.clone()).unwrap();
+        assert_eq!(difference.data, (0..3 * 16 * 8).map(|n| n % 8).collect::<Vec<i64>>());
+
+        let mut doubled = sum.clone();
+        doubled.apply_mut(|n| *n *= 2);
+        assert_eq!(doubled.data, sum.data.iter().map(|n| n * 2).collect::<Vec<i64>>());
     }
 
     #[test]
     fn test_calc_next_idx() {
-        //println!("{:?}", calc_next_idx(&vec![3, 3], &vec![2,1]))
+        assert_eq!(calc_next_idx(&[3, 3], &[2, 1]), Some(vec![2, 2]));
+        assert_eq!(calc_next_idx(&[3, 3], &[0, 2]), Some(vec![1, 0]));
+        assert_eq!(calc_next_idx(&[3, 3], &[2, 2]), None);
+        assert_eq!(calc_next_idx(&[], &[]), None);
     }
 
 /*
@@ -289,6 +384,50 @@ mod test_matrix_functionality {
         }
     }
 
+    #[test]
+    fn test_multiply() {
+        let mat1 : Matrix<i32> = Matrix::from_iter(vec![3, 2], 1.., Layout::RowMajor);
+        let mat2 : Matrix<i32> = Matrix::from_iter(vec![3, 2], 1.., Layout::RowMajor);
+        let (mat_multiply, _mat1, _mat2) = multiply(mat1, mat2).unwrap();
+        assert_eq!(mat_multiply.data, vec![1, 4, 9, 16, 25, 36]);
+
+        // The mask gets broadcasted over the rows
+        let mat1 : Matrix<i32> = Matrix::from_iter(vec![3, 2], 1.., Layout::RowMajor);
+        let mask : Matrix<i32> = Matrix::from_iter(vec![3, 1], vec![1, 0, 1], Layout::RowMajor);
+        let (mat_multiply, _mat1, _mask) = multiply(mat1, mask).unwrap();
+        assert_eq!(mat_multiply.data, vec![1, 2, 0, 0, 5, 6]);
+        assert_eq!(mat_multiply.shape, vec![3, 2]);
+    }
+
+    #[test]
+    fn test_if_multiply_throws_error_when_bounds_are_incompatible() {
+        let mat1 : Matrix<i32> = Matrix::from_iter(vec![3, 2], 1.., Layout::RowMajor);
+        let mat2 : Matrix<i32> = Matrix::from_iter(vec![2, 2], 1.., Layout::RowMajor);
+        match multiply(mat1, mat2) {
+            Ok((_, _, _)) => panic!("Shouldn't have gotten to here"),
+            Err(err) => assert_eq!(MatrixError::BroadcastError, err)
+        }
+    }
+
+    #[test]
+    fn test_divide_and_remainder() {
+        let mat1 : Matrix<i32> = Matrix::from_iter(vec![2, 3], vec![9, 10, -11, 12, 13, 14], Layout::RowMajor);
+        let mat2 : Matrix<i32> = Matrix::from_iter(vec![3], vec![2, 3, 4], Layout::RowMajor);
+        let (mat_divide, _mat1, _mat2) = divide(mat1, mat2).unwrap();
+        assert_eq!(mat_divide.data, vec![4, 3, -2, 6, 4, 3]);
+
+        let mat1 : Matrix<i32> = Matrix::from_iter(vec![2, 3], vec![9, 10, -11, 12, 13, 14], Layout::RowMajor);
+        let mat2 : Matrix<i32> = Matrix::from_iter(vec![3], vec![2, 3, 4], Layout::RowMajor);
+        let (mat_remainder, _mat1, _mat2) = remainder(mat1, mat2).unwrap();
+        assert_eq!(mat_remainder.data, vec![1, 1, -3, 0, 1, 2]);
+
+        let zeros : Matrix<i32> = Matrix::from_iter(vec![2, 3], vec![1, 1, 1, 1, 0, 1], Layout::RowMajor);
+        match remainder(mat_divide, zeros) {
+            Ok((_, _, _)) => panic!("Shouldn't have gotten to here"),
+            Err(err) => assert_eq!(MatrixError::OpError, err)
+        }
+    }
+
     #[test]
     fn test_concat() {
         let mat1 = Matrix::from_iter(vec![2, 3], vec![0; 6], Layout::RowMajor);
@@ -324,44 +463,44 @@ mod test_matrix_functionality {
     fn test_mul_2d() {
         let mut mat1 = Matrix::from_iter(vec![2, 2], 5.., Layout::RowMajor);
 
-        match mat1.set(&vec![0, 0], 1){
+        match mat1.set(&[0, 0], 1){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat1.set(&vec![0, 1], 2){
+        match mat1.set(&[0, 1], 2){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat1.set(&vec![1, 0], 3){
+        match mat1.set(&[1, 0], 3){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat1.set(&vec![1, 1], 4){
+        match mat1.set(&[1, 1], 4){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
         let mut mat2 = Matrix::from_iter(vec![2, 2], 5.., Layout::RowMajor);
 
-        match mat2.set(&vec![0, 0], 5){
+        match mat2.set(&[0, 0], 5){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat2.set(&vec![0, 1], 6){
+        match mat2.set(&[0, 1], 6){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat2.set(&vec![1, 0], 0){
+        match mat2.set(&[1, 0], 0){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
 
-        match mat2.set(&vec![1, 1], 7){
+        match mat2.set(&[1, 1], 7){
             Ok(_) => {},
             Err(err) => panic!("{err}")
         }
@@ -378,10 +517,1414 @@ mod test_matrix_functionality {
         for (item, idx) in matrix_iter {
             println!("{idx:?} -> {item}");
         }
+        assert_eq!(matmul.data, vec![5, 20, 15, 46]);
+    }
+
+    #[test]
+    fn test_mul_1d() {
+        let mat1 = Matrix::from_iter(vec![4], vec![1, 2, 3, 4], Layout::RowMajor);
+        let mat2 = Matrix::from_iter(vec![4], vec![5, 6, 0, 7], Layout::RowMajor);
+        let (result, mat1, _mat2) = multiply_1d(mat1, mat2).unwrap();
+        assert_eq!(result, 45);
+
+        let mat3 = Matrix::from_iter(vec![3], vec![5, 6, 0], Layout::RowMajor);
+        match multiply_1d(mat1, mat3) {
+            Ok((_, _, _)) => panic!("Shouldn't have gotten to here"),
+            Err(err) => assert_eq!(MatrixError::MatmulShapeError, err)
+        }
+    }
+
+    #[test]
+    fn test_dot_outer_inner() {
+        let mat1 = Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor);
+        let mat2 = Matrix::from_iter(vec![3], vec![4, -5, 6], Layout::RowMajor);
+        assert_eq!(dot(&mat1, &mat2).unwrap(), 12);
+
+        let result = outer(&mat1, &mat2);
+        assert_eq!(result.shape(), &vec![3, 3]);
+        assert_eq!(result.data, vec![4, -5, 6, 8, -10, 12, 12, -15, 18]);
+
+        // The rows of the transposed matrix are the columns of the original one
+        let mut mat3 = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        mat3.transpose();
+        let result = inner(&mat3, &Matrix::from_iter(vec![2], vec![1, -1], Layout::RowMajor), 1).unwrap();
+        assert_eq!(result.shape(), &vec![3]);
+        assert_eq!(result.data, vec![-3, -3, -3]);
+
+        match inner(&mat1, &mat2, 1) {
+            Ok(_) => panic!("Shouldn't have gotten to here"),
+            Err(err) => assert_eq!(MatrixError::DimError, err)
+        }
+    }
+
+    #[test]
+    fn test_tensordot() {
+        let mat1: Matrix<i32> = Matrix::from_iter(vec![2, 3, 4], 0.., Layout::RowMajor);
+        let mat2: Matrix<i32> = Matrix::from_iter(vec![4, 3], 0.., Layout::RowMajor);
+
+        // result[i] = sum over j, k of mat1[i, j, k] * mat2[k, j]
+        let result = tensordot(&mat1, &mat2, &[1, 2], &[1, 0]).unwrap();
+        assert_eq!(result.shape(), &vec![2]);
+        let mut expected = vec![0; 2];
+        for (i, item) in expected.iter_mut().enumerate() {
+            for j in 0..3 {
+                for k in 0..4 {
+                    *item += mat1.get_copy(&[i, j, k]).unwrap() * mat2.get_copy(&[k, j]).unwrap();
+                }
+            }
+        }
+        assert_eq!(result.data, expected);
+
+        // Without any contracted axes the result is the outer product
+        let result = tensordot(&mat2, &mat2, &[], &[]).unwrap();
+        assert_eq!(result.shape(), &vec![4, 3, 4, 3]);
+        assert_eq!(result.get_copy(&[1, 2, 3, 1]).unwrap(), 5 * 10);
+
+        // Contracting over the first axis is the same as a matmul with the transposed matrix
+        let mat3: Matrix<i32> = Matrix::from_iter(vec![4, 2], 1.., Layout::RowMajor);
+        let mut mat2_t = mat2.clone();
+        mat2_t.transpose();
+        assert_eq!(tensordot(&mat2, &mat3, &[0], &[0]).unwrap().data, matmul(&mat2_t, &mat3).unwrap().data);
+    }
+
+    #[test]
+    fn test_if_tensordot_throws_error_when_axes_are_invalid() {
+        let mat1: Matrix<i32> = Matrix::from_iter(vec![2, 3], 0.., Layout::RowMajor);
+        let mat2: Matrix<i32> = Matrix::from_iter(vec![3, 2], 0.., Layout::RowMajor);
+        assert_eq!(tensordot(&mat1, &mat2, &[1], &[]).unwrap_err(), MatrixError::DimError);
+        assert_eq!(tensordot(&mat1, &mat2, &[2], &[0]).unwrap_err(), MatrixError::DimError);
+        assert_eq!(tensordot(&mat1, &mat2, &[1, 1], &[0, 1]).unwrap_err(), MatrixError::DimError);
+        assert_eq!(tensordot(&mat1, &mat2, &[0], &[0]).unwrap_err(), MatrixError::MatmulShapeError);
+    }
+
+    #[test]
+    fn test_matmul_non_square() {
+        let mat1 = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        let mat2 = Matrix::from_iter(vec![3, 4], 1.., Layout::RowMajor);
+
+        let result = matmul(&mat1, &mat2).unwrap();
+        assert_eq!(result.shape, vec![2, 4]);
+        assert_eq!(result.data, vec![38, 44, 50, 56, 83, 98, 113, 128]);
+
+        // The strides of a transposed matrix must be respected
+        let mut mat3 = Matrix::from_iter(vec![4, 3], 1.., Layout::RowMajor);
+        mat3.transpose();
+        let result = matmul(&mat1, &mat3).unwrap();
+        assert_eq!(result.shape, vec![2, 4]);
+        assert_eq!(result.data, vec![14, 32, 50, 68, 32, 77, 122, 167]);
+    }
+
+    #[test]
+    fn test_matmul_vectors() {
+        let mat = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        let vec_3 = Matrix::from_iter(vec![3], vec![1, 0, 2], Layout::RowMajor);
+        let vec_2 = Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor);
+
+        let result = matmul(&mat, &vec_3).unwrap();
+        assert_eq!(result.shape, vec![2]);
+        assert_eq!(result.data, vec![7, 16]);
+
+        let result = matmul(&vec_2, &mat).unwrap();
+        assert_eq!(result.shape, vec![3]);
+        assert_eq!(result.data, vec![9, 12, 15]);
+
+        let result = matmul(&vec_3, &vec_3).unwrap();
+        assert_eq!(result.shape, vec![1]);
+        assert_eq!(result.data, vec![5]);
+    }
+
+    #[test]
+    fn test_matmul_batched() {
+        // [2, 2, 2] @ [2, 2] broadcasts the rhs over the batch
+        let mat1 = Matrix::from_iter(vec![2, 2, 2], 1.., Layout::RowMajor);
+        let mat2 = Matrix::from_iter(vec![2, 2], vec![1, 0, 0, 2], Layout::RowMajor);
+
+        let result = matmul(&mat1, &mat2).unwrap();
+        assert_eq!(result.shape, vec![2, 2, 2]);
+        assert_eq!(result.data, vec![1, 4, 3, 8, 5, 12, 7, 16]);
+
+        // [2, 1, 1, 2] @ [3, 2, 1] results in [2, 3, 1, 1]
+        let mat1 = Matrix::from_iter(vec![2, 1, 1, 2], 1.., Layout::RowMajor);
+        let mat2 = Matrix::from_iter(vec![3, 2, 1], 1.., Layout::RowMajor);
+
+        let result = matmul(&mat1, &mat2).unwrap();
+        assert_eq!(result.shape, vec![2, 3, 1, 1]);
+        assert_eq!(result.data, vec![5, 11, 17, 11, 25, 39]);
+    }
+
+    #[test]
+    fn test_try_matmul_and_try_zip_with() {
+        let lhs: Matrix<i32> = Matrix::from_iter(vec![2, 2], vec![1, 2, 3, 4], Layout::RowMajor);
+        let rhs: Matrix<i32> = Matrix::from_iter(vec![2], vec![10, 100], Layout::RowMajor);
+        let checked_mul = |lhs: &i32, rhs: &i32| lhs.checked_mul(*rhs).ok_or(MatrixError::OpError);
+        let checked_add = |lhs: i32, rhs: i32| lhs.checked_add(rhs).ok_or(MatrixError::OpError);
+
+        // Without errors the results are the same as the ones of matmul() and zip_with()
+        assert_eq!(try_matmul(&lhs, &rhs, checked_mul, checked_add).unwrap().data, matmul(&lhs, &rhs).unwrap().data);
+        assert_eq!(try_zip_with(&lhs, &rhs, checked_add).unwrap().data, (&lhs + &rhs).data);
+
+        let big: Matrix<i32> = Matrix::from_iter(vec![2], vec![i32::MAX, 1], Layout::RowMajor);
+        assert_eq!(Err(MatrixError::OpError), try_matmul(&lhs, &big, checked_mul, checked_add).map(|m| m.data));
+        assert_eq!(Err(MatrixError::OpError), try_zip_with(&lhs, &big, checked_add).map(|m| m.data));
+
+        // Shape errors are converted into the error type of the functions
+        let column: Matrix<i32> = Matrix::from_iter(vec![3], 1.., Layout::RowMajor);
+        assert_eq!(Err(MatrixError::MatmulShapeError), try_matmul(&lhs, &column, checked_mul, checked_add).map(|m| m.data));
+        assert_eq!(Err(MatrixError::BroadcastError), try_zip_with(&lhs, &column, checked_add).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_if_matmul_throws_error_when_shapes_are_incompatible() {
+        let mat1: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        let mat2: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        assert_eq!(Err(MatrixError::MatmulShapeError), matmul(&mat1, &mat2).map(|m| m.data));
+
+        let mat1: Matrix<i32> = Matrix::from_iter(vec![2, 2, 3], 1.., Layout::RowMajor);
+        let mat2: Matrix<i32> = Matrix::from_iter(vec![3, 3, 2], 1.., Layout::RowMajor);
+        assert_eq!(Err(MatrixError::MatmulShapeError), matmul(&mat1, &mat2).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_slice() {
+        let mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 0.., Layout::RowMajor);
+
+        let view = mat.slice(&[Slice::range(1, 3), Slice::step(0, 4, 2)]).unwrap();
+        assert_eq!(view.shape(), &vec![2, 2]);
+        assert_eq!(view.to_matrix().data, vec![4, 6, 8, 10]);
+
+        // Slicing a view slices the original data
+        let column = view.slice(&[Slice::full(), Slice::Index(1)]).unwrap();
+        assert_eq!(column.shape(), &vec![2]);
+        assert_eq!(column.to_matrix().data, vec![6, 10]);
+
+        // NewAxis doesn't consume a dimension
+        let view = mat.slice(&[Slice::Index(0), Slice::NewAxis]).unwrap();
+        assert_eq!(view.shape(), &vec![1, 4]);
+        assert_eq!(view.get(&[0, 3]), Ok(&3));
+
+        // The strides of a transposed matrix are respected
+        let mut transposed = mat.clone();
+        transposed.transpose();
+        let view = transposed.slice(&[Slice::Index(1)]).unwrap();
+        assert_eq!(view.to_matrix().data, vec![1, 5, 9]);
+    }
+
+    #[test]
+    fn test_if_slice_throws_error_when_slice_is_invalid() {
+        let mat: Matrix<i32> = Matrix::from_iter(vec![3, 4], 0.., Layout::RowMajor);
+
+        assert_eq!(Err(MatrixError::SliceError), mat.slice(&[Slice::range(0, 4)]).map(|v| v.size()));
+        assert_eq!(Err(MatrixError::SliceError), mat.slice(&[Slice::range(2, 2)]).map(|v| v.size()));
+        assert_eq!(Err(MatrixError::SliceError), mat.slice(&[Slice::step(0, 3, 0)]).map(|v| v.size()));
+        assert_eq!(Err(MatrixError::SliceError), mat.slice(&[Slice::Index(0), Slice::Index(0), Slice::Index(0)]).map(|v| v.size()));
+        assert_eq!(Err(MatrixError::ViewError), mat.as_strided(&[4, 4], &[4, 1], 0).map(|v| v.size()));
+    }
+
+    #[test]
+    fn test_slice_mut() {
+        let mut mat: Matrix<i32> = Matrix::from_iter(vec![3, 3], 0.., Layout::RowMajor);
+        let mut view = mat.slice_mut(&[Slice::step(0, 3, 2), Slice::range(1, 3)]).unwrap();
+        view.fill(-1);
+        assert_eq!(Ok(()), view.set(&[1, 1], 100));
+        assert_eq!(mat.data, vec![0, -1, -1, 3, 4, 5, 6, -1, 100]);
+
+        let src: Matrix<i32> = Matrix::from_iter(vec![3], 10.., Layout::RowMajor);
+        let mut row = mat.slice_mut(&[Slice::Index(1)]).unwrap();
+        assert_eq!(Ok(()), row.assign(&src.view()));
+        assert_eq!(mat.data, vec![0, -1, -1, 10, 11, 12, 6, -1, 100]);
+    }
+
+    #[test]
+    fn test_empty_views() {
+        let mut mat: Matrix<i32> = Matrix::new(vec![2, 0], Layout::RowMajor);
+        assert_eq!(mat.view().to_matrix().shape(), &vec![2, 0]);
+        assert_eq!(outer(&mat, &mat).shape(), &vec![0, 0]);
+
+        // Non-contiguous empty matrices get copied through a view as well
+        mat.transpose();
+        assert_eq!(mat.view().to_matrix().shape(), &vec![0, 2]);
+        assert!(mat.to_bytes().is_ok());
+
+        let mut view = mat.view_mut();
+        view.fill(1);
+        let src: Matrix<i32> = Matrix::new(vec![0, 2], Layout::RowMajor);
+        assert_eq!(Ok(()), view.assign(&src.view()));
+    }
+
+    #[test]
+    fn test_sum() {
+        let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3, 2], 1.., Layout::RowMajor);
+
+        let result = mat.sum(1, false).unwrap();
+        assert_eq!(result.shape, vec![2, 2]);
+        assert_eq!(result.data, vec![9, 12, 27, 30]);
+
+        let result = mat.sum(2, true).unwrap();
+        assert_eq!(result.shape, vec![2, 3, 1]);
+        assert_eq!(result.data, vec![3, 7, 11, 15, 19, 23]);
+
+        // Reducing a vector results in shape [1]
+        let vector: Matrix<i32> = Matrix::from_iter(vec![4], 1.., Layout::RowMajor);
+        let result = vector.sum(0, false).unwrap();
+        assert_eq!(result.shape, vec![1]);
+        assert_eq!(result.data, vec![10]);
+
+        // The strides of a transposed matrix are respected
+        let mut transposed: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        transposed.transpose();
+        assert_eq!(transposed.sum(1, false).unwrap().data, vec![5, 7, 9]);
+    }
+
+    #[test]
+    fn test_reductions() {
+        let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], vec![3, -1, 4, 1, 5, -9], Layout::RowMajor);
+
+        assert_eq!(mat.prod(0, false).unwrap().data, vec![3, -5, -36]);
+        assert_eq!(mat.max(1, false).unwrap().data, vec![4, 5]);
+        assert_eq!(mat.min(0, true).unwrap().data, vec![1, -1, -9]);
+        assert_eq!(mat.argmax(1, false).unwrap().data, vec![2, 1]);
+        assert_eq!(mat.argmax(0, true).unwrap().shape, vec![1, 3]);
+        assert_eq!(mat.mean(1, false).unwrap().data, vec![2, -1]);
+    }
+
+    #[test]
+    fn test_if_reductions_throw_error_when_axis_is_invalid() {
+        let mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+
+        assert_eq!(Err(MatrixError::DimError), mat.sum(2, false).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.argmax(5, true).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.mean(2, true).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_reductions_of_empty_matrices() {
+        // The kept shape is empty, so the result is empty as well
+        let mat: Matrix<i32> = Matrix::new(vec![0, 3], Layout::RowMajor);
+        let result = mat.sum(1, false).unwrap();
+        assert_eq!(result.shape, vec![0]);
+        assert!(result.data.is_empty());
+        assert_eq!(mat.argmax(1, true).unwrap().shape, vec![0, 1]);
+    }
+
+    #[test]
+    fn test_if_reductions_throw_error_when_axis_is_empty() {
+        let mat: Matrix<i32> = Matrix::new(vec![2, 0], Layout::RowMajor);
+
+        assert_eq!(Err(MatrixError::DimError), mat.sum(1, false).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.prod(1, true).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.max(1, false).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.argmax(1, false).map(|m| m.data));
+        assert_eq!(Err(MatrixError::DimError), mat.mean(1, false).map(|m| m.data));
+
+        let mat: Matrix<i32> = Matrix::new(vec![0, 3], Layout::RowMajor);
+        assert_eq!(Err(MatrixError::DimError), mat.sum(0, false).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_operators() {
+        let lhs: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        let rhs: Matrix<i32> = Matrix::from_iter(vec![3], vec![1, 0, -1], Layout::RowMajor);
+
+        assert_eq!((&lhs + &rhs).data, vec![2, 2, 2, 5, 5, 5]);
+        assert_eq!((&lhs - &rhs).data, vec![0, 2, 4, 3, 5, 7]);
+        assert_eq!((&lhs * &rhs).data, vec![1, 0, -3, 4, 0, -6]);
+        assert_eq!((-&lhs).data, vec![-1, -2, -3, -4, -5, -6]);
+        assert_eq!((&lhs * 2 - 1).data, vec![1, 3, 5, 7, 9, 11]);
+
+        // Operands are broadcast on both sides
+        let column: Matrix<i32> = Matrix::from_iter(vec![2, 1], vec![10, 20], Layout::RowMajor);
+        let result = rhs.clone() + column;
+        assert_eq!(result.shape, vec![2, 3]);
+        assert_eq!(result.data, vec![11, 10, 9, 21, 20, 19]);
+
+        // Transposed operands are read through their strides
+        let mut transposed = lhs.clone();
+        transposed.transpose();
+        assert_eq!((&transposed + 0).data, transposed.data);
+        assert_eq!((&transposed + &Matrix::from_iter(vec![1], vec![0], Layout::RowMajor)).data, vec![1, 4, 2, 5, 3, 6]);
+    }
+
+    #[test]
+    fn test_operators_with_empty_operands() {
+        let empty: Matrix<i32> = Matrix::new(vec![0], Layout::RowMajor);
+        let result = &empty + &empty;
+        assert_eq!(result.shape, vec![0]);
+        assert!(result.data.is_empty());
+
+        // The empty operand is broadcast against the other one
+        let lhs: Matrix<i32> = Matrix::new(vec![2, 0], Layout::RowMajor);
+        let rhs: Matrix<i32> = Matrix::from_iter(vec![2, 1], vec![1, 2], Layout::RowMajor);
+        assert_eq!((&lhs * &rhs).shape, vec![2, 0]);
+
+        let mut mat = lhs.clone();
+        mat -= &rhs;
+        assert_eq!(mat.shape, vec![2, 0]);
+    }
+
+    #[test]
+    fn test_assign_operators() {
+        let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1.., Layout::RowMajor);
+        let row: Matrix<i32> = Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor);
+
+        mat += &row;
+        assert_eq!(mat.data, vec![2, 4, 4, 6]);
+        mat -= row;
+        assert_eq!(mat.data, vec![1, 2, 3, 4]);
+        mat *= 3;
+        assert_eq!(mat.data, vec![3, 6, 9, 12]);
+        mat += 1;
+        assert_eq!(mat.data, vec![4, 7, 10, 13]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_if_assign_operator_panics_when_lhs_would_be_broadcast() {
+        let mut row: Matrix<i32> = Matrix::from_iter(vec![2], 1.., Layout::RowMajor);
+        let mat: Matrix<i32> = Matrix::from_iter(vec![2, 2], 1.., Layout::RowMajor);
+        row += mat;
+    }
+
+    #[test]
+    fn test_json_serialization() {
+        let mut mat: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        mat.transpose();
+
+        let loaded: Matrix<i32> = Matrix::from_json(&mat.to_json().unwrap()).unwrap();
+        assert_eq!(loaded.shape, vec![3, 2]);
+        assert_eq!(loaded.strides, mat.strides);
+        assert_eq!(loaded.layout, Layout::RowMajor);
+        assert_eq!(loaded.get(&[2, 1]), Ok(&6));
+
+        // Data which doesn't match the shape is rejected
+        let json = r#"{"shape":[2,2],"strides":[2,1],"data":[1,2,3],"layout":"RowMajor","size":4}"#;
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i32>::from_json(json).map(|m| m.data));
+
+        // So are strides which overflow when looking for the last element
+        let json = r#"{"shape":[2,2],"strides":[18446744073709551615,1],"data":[1,2,3,4],"layout":"RowMajor","size":4}"#;
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i32>::from_json(json).map(|m| m.data));
+        let json = r#"{"shape":[2,2],"strides":[9223372036854775807,9223372036854775807],"data":[1,2,3,4],"layout":"RowMajor","size":4}"#;
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i32>::from_json(json).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_binary_serialization() {
+        let mat: Matrix<i64> = Matrix::from_iter(vec![2, 2, 3], -6.., Layout::ColumnMajor);
+        let path = std::env::temp_dir().join("cryptonic_test_binary_serialization.bin");
+
+        mat.save(&path).unwrap();
+        let loaded: Matrix<i64> = Matrix::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.shape, mat.shape);
+        assert_eq!(loaded.strides, mat.strides);
+        assert_eq!(loaded.data, mat.data);
+
+        // Broadcast strides aren't contiguous, so the matrix gets written in row major order
+        let mut broadcasted: Matrix<i64> = Matrix::from_iter(vec![3], 1.., Layout::RowMajor);
+        broadcasted.set_shape(&[2, 3]);
+        broadcasted.set_strides(&[0, 1]);
+        let loaded: Matrix<i64> = Matrix::from_bytes(&broadcasted.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.data, vec![1, 2, 3, 1, 2, 3]);
+
+        // Wrong element type, truncated data and unknown versions are rejected
+        let mut bytes = mat.to_bytes().unwrap();
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i32>::from_bytes(&bytes).map(|m| m.data));
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i64>::from_bytes(&bytes[..bytes.len() - 1]).map(|m| m.data));
+        bytes[4] += 1;
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<i64>::from_bytes(&bytes).map(|m| m.data));
+    }
+
+    // Builds a .npy file the same way numpy.save() does
+    fn build_npy(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = header.to_string();
+        header.push_str(&" ".repeat(64 - (10 + header.len() + 1) % 64));
+        header.push('\n');
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_read_npy() {
+        // numpy.arange(6, dtype="<i4").reshape(2, 3)
+        let data: Vec<u8> = (0..6i32).flat_map(|n| n.to_le_bytes()).collect();
+        let bytes = build_npy("{'descr': '<i4', 'fortran_order': False, 'shape': (2, 3), }", &data);
+        let mat: Matrix<i32> = Matrix::read_npy(&mut bytes.as_slice()).unwrap();
+        assert_eq!(mat.shape, vec![2, 3]);
+        assert_eq!(mat.layout, Layout::RowMajor);
+        assert_eq!(mat.get(&[1, 0]), Ok(&3));
+
+        // Big endian float64 in Fortran order
+        let data: Vec<u8> = [0.5f64, 1.5, 2.5, 3.5].iter().flat_map(|n| n.to_be_bytes()).collect();
+        let bytes = build_npy("{'descr': '>f8', 'fortran_order': True, 'shape': (2, 2), }", &data);
+        let mat: Matrix<f64> = Matrix::read_npy(&mut bytes.as_slice()).unwrap();
+        assert_eq!(mat.layout, Layout::ColumnMajor);
+        assert_eq!(mat.get(&[0, 1]), Ok(&2.5));
+
+        // The dtype must match the type of the matrix
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<f32>::read_npy(&mut bytes.as_slice()).map(|m| m.data));
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<f64>::read_npy(&mut &bytes[..bytes.len() - 1]).map(|m| m.data));
+
+        // A shape whose size overflows once multiplied with the element size is rejected
+        let bytes = build_npy("{'descr': '<f8', 'fortran_order': False, 'shape': (4611686018427387904,), }", &[]);
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<f64>::read_npy(&mut bytes.as_slice()).map(|m| m.data));
+
+        // A huge header length is rejected before the header gets read
+        let mut bytes = b"\x93NUMPY\x02\x00".to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(Err(MatrixError::SerializationError), Matrix::<f64>::read_npy(&mut bytes.as_slice()).map(|m| m.data));
+    }
+
+    #[test]
+    fn test_npy_and_npz_round_trip() {
+        let weights: Matrix<f32> = Matrix::from_iter(vec![2, 3], [0.25f32, -1.0, 2.0, 3.5, 4.0, -0.5], Layout::RowMajor);
+        let biases: Matrix<f32> = Matrix::from_iter(vec![3], vec![1.0; 3], Layout::RowMajor);
+
+        let bytes = weights.to_npy_bytes();
+        // The data must start at a multiple of 64 bytes
+        assert_eq!((bytes.len() - 6 * 4) % 64, 0);
+        assert_eq!(Matrix::<f32>::read_npy(&mut bytes.as_slice()).unwrap().data, weights.data);
+
+        let mut archive = std::io::Cursor::new(Vec::new());
+        Matrix::write_npz(&mut archive, &[("w1", &weights), ("b1", &biases)]).unwrap();
+        archive.set_position(0);
+        let arrays = Matrix::<f32>::read_npz(archive).unwrap();
+        assert_eq!(arrays.len(), 2);
+        assert_eq!(arrays["w1"].shape, vec![2, 3]);
+        assert_eq!(arrays["w1"].data, weights.data);
+        assert_eq!(arrays["b1"].data, biases.data);
+    }
+}
+
+
+#[cfg(test)]
+mod test_ciphertext {
+    use std::error::Error;
+    use std::sync::{Arc, OnceLock};
+    use tfhe::shortint::prelude::*;
+    use tfhe::shortint::CompressedPublicKey;
+    use Cryptonic::cryptography::ciphtxt::{BudgetMode, CipherTextType};
+    use Cryptonic::cryptography::errors::CryptoError;
+    use Cryptonic::cryptography::inference::{Client, Encryptor, InferenceRequest, InferenceResponse, Server};
+    use Cryptonic::cryptography::key_gen::{custom_gen_keys, find_preset, get_preset, validate_parameters, SecurityPolicy, MY_PARAM, PRESETS};
+    use Cryptonic::cryptography::radix::RadixCipherText;
+    use Cryptonic::cryptography::key_store::{key_from_bytes, key_to_bytes, KeyStore, KEY_FORMAT_VERSION};
+    use Cryptonic::neural_network::activations::{activate, Activate, Activation};
+    use Cryptonic::neural_network::dense_layer::DenseLayer;
+    use Cryptonic::neural_network::errors::NnetError;
+    use Cryptonic::neural_network::layer_trait::Layer;
+    use Cryptonic::neural_network::layer_type::LayerType;
+    use Cryptonic::neural_network::merge::Merge;
+    use Cryptonic::neural_network::nnet::Nnet;
+    use Cryptonic::tensor_library::layout::Layout;
+    use Cryptonic::tensor_library::matrix::Matrix;
+
+    fn key_store() -> KeyStore {
+        KeyStore::new(concat!(env!("CARGO_TARGET_TMPDIR"), "/keys")).unwrap()
+    }
+
+    // Key generation is slow, so all tests share the same keys and they're only generated on the
+    // first run
+    fn keys() -> &'static (ClientKey, Arc<ServerKey>) {
+        static KEYS: OnceLock<(ClientKey, Arc<ServerKey>)> = OnceLock::new();
+        KEYS.get_or_init(|| {
+            let (client_key, server_key) = key_store()
+                .get_or_generate("message_2_carry_2", &PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure)
+                .unwrap();
+            (client_key, Arc::new(server_key))
+        })
+    }
+
+    // The same network is built for both element types
+    macro_rules! build_network {
+        () => {{
+            let dense_layer = |weights: Vec<i32>, biases: Vec<i32>| {
+                let weights = Matrix::from_iter(vec![2, 2], weights, Layout::RowMajor);
+                let biases = Matrix::from_iter(vec![2], biases, Layout::RowMajor);
+                LayerType::DenseLayer(DenseLayer::new("dense", weights, biases, None).unwrap())
+            };
+            let mut network = Nnet::new();
+            let id1 = network.add_layer(dense_layer(vec![1, 0, 0, 1], vec![0, 1]));
+            let id2 = network.add_layer(dense_layer(vec![1, 2, -1, 3], vec![1, -2]));
+            network.add_link(None, Some(id1)).unwrap();
+            network.add_link(Some(id1), Some(id2)).unwrap();
+            network.add_link(Some(id2), None).unwrap();
+            network
+        }};
+    }
+
+    fn encrypt(value: u64) -> CipherTextType {
+        let (client_key, server_key) = keys();
+        CipherTextType::encrypt(value, client_key, server_key)
+    }
+
+    fn decrypt(value: &CipherTextType) -> u64 {
+        value.decrypt(&keys().0)
+    }
+
+    #[test]
+    fn test_ciphertext_arithmetic() {
+        let (lhs, rhs) = (encrypt(3), encrypt(2));
+
+        // Everything is computed modulo the message modulus, which is 4 here
+        assert_eq!(decrypt(&(lhs.clone() + rhs.clone())), 1);
+        assert_eq!(decrypt(&(lhs.clone() - rhs.clone())), 1);
+        assert_eq!(decrypt(&(rhs.clone() - lhs.clone())), 3);
+        assert_eq!(decrypt(&(lhs.clone() * rhs.clone())), 2);
+        assert_eq!(decrypt(&-rhs.clone()), 2);
+        assert_eq!(decrypt(&-lhs.clone()), 1);
+
+        assert_eq!(decrypt(&(lhs.clone() + 2)), 1);
+        assert_eq!(decrypt(&(lhs.clone() - 1)), 2);
+        assert_eq!(decrypt(&(lhs.clone() * 3)), 1);
+
+        let mut acc = lhs.clone();
+        acc += rhs.clone();
+        acc *= rhs.clone();
+        acc -= lhs;
+        acc += 1;
+        assert_eq!(decrypt(&acc), 0);
+    }
+
+    #[test]
+    fn test_ciphertext_signed_scalars() {
+        let value = encrypt(3);
+
+        // Negative scalars are reduced modulo 4
+        assert_eq!(decrypt(&(value.clone() * -1)), 1);
+        assert_eq!(decrypt(&(value.clone() + -2)), 1);
+        assert_eq!(decrypt(&(value.clone() - -3)), 2);
+        assert_eq!(decrypt(&(-3 * value.clone())), 3);
+        assert_eq!(decrypt(&(7 + value.clone())), 2);
+
+        // Scalars which don't fit in a u8 take a different path. The modulus is raised, so that
+        // they aren't reduced first, the ciphertext itself still works modulo 4.
+        let mut big_modulus = value.clone();
+        big_modulus.Modulus = Some(1024);
+        assert_eq!(decrypt(&(big_modulus.clone() * 301)), 3);
+        assert_eq!(decrypt(&(big_modulus * 302)), 2);
+    }
+
+    #[test]
+    fn test_ciphertext_budget() {
+        let value = encrypt(3);
+        assert_eq!((value.noise_level(), value.degree()), (1, 3));
+        assert!(value.max_noise_level() > 1);
+
+        // The carry buffer only holds a few sums, the operands are bootstrapped when it's full
+        let mut sum = value.clone();
+        let mut product = value.clone();
+        for _ in 0..19 {
+            sum += value.clone();
+            product *= 3;
+            assert!(sum.is_within_budget() && product.is_within_budget());
+            assert!(sum.noise_level() <= sum.max_noise_level());
+        }
+        assert_eq!(decrypt(&sum), 0); // 20 * 3 % 4
+        assert_eq!(decrypt(&product), 1); // 3^20 % 4
+
+        let bootstrapped = sum.bootstrap().unwrap();
+        assert_eq!((bootstrapped.noise_level(), decrypt(&bootstrapped)), (1, 0));
+        assert!(bootstrapped.degree() < 4);
+
+        // In unchecked mode nothing is bootstrapped, so the budget runs out
+        let mut unchecked = value.clone();
+        unchecked.set_budget_mode(BudgetMode::Unchecked);
+        let small_sum = unchecked.clone() + unchecked.clone();
+        assert!(small_sum.is_within_budget());
+        assert_eq!(decrypt(&small_sum), 2);
+
+        let mut sum = unchecked.clone();
+        for _ in 0..7 {
+            sum += unchecked.clone();
+        }
+        assert_eq!(sum.noise_level(), 8);
+        assert!(!sum.is_within_budget());
+
+        // The result is only unchecked if both operands are
+        let mut mixed = unchecked.clone();
+        for _ in 0..7 {
+            mixed += value.clone();
+        }
+        assert!(mixed.is_within_budget());
+        assert_eq!(decrypt(&mixed), 0); // 8 * 3 % 4
+    }
+
+    #[test]
+    fn test_ciphertext_deserialized_noise() {
+        let (_client_key, server_key) = keys();
+        let mut sum = encrypt(3);
+        for _ in 0..2 {
+            sum += encrypt(3);
+        }
+        assert_eq!(sum.noise_level(), 3);
+
+        // The noise level isn't sent, the server assumes the worst once it attaches its key
+        let mut received: CipherTextType = bincode::deserialize(&bincode::serialize(&sum).unwrap()).unwrap();
+        assert_eq!(received.noise_level(), 0);
+        received.set_server_key(server_key).unwrap();
+        assert_eq!(received.noise_level(), received.max_noise_level());
+        let result = received + encrypt(2);
+        assert!(result.is_within_budget());
+        assert_eq!(decrypt(&result), 3); // (9 + 2) % 4
+
+        // A huge noise level saturates instead of overflowing
+        let mut unchecked = encrypt(1);
+        unchecked.set_budget_mode(BudgetMode::Unchecked);
+        unchecked.NoiseLevel = usize::MAX;
+        assert_eq!((unchecked.clone() + unchecked.clone()).noise_level(), usize::MAX);
+        assert_eq!(unchecked.checked_scalar_mul(3).unwrap().noise_level(), usize::MAX);
+    }
+
+    #[test]
+    fn test_encrypted_nnet_forward() {
+        let mut plain_network: Nnet<i32> = build_network!();
+        let plain_result = plain_network.forward(Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor)).unwrap();
+
+        let mut encrypted_network: Nnet<CipherTextType> = build_network!();
+        let input = Matrix::from_iter(vec![2], vec![encrypt(1), encrypt(2)], Layout::RowMajor);
+        let encrypted_result = encrypted_network.forward(input).unwrap();
+
+        let decrypted: Vec<u64> = encrypted_result.data.iter().map(decrypt).collect();
+        let expected: Vec<u64> = plain_result.data.iter().map(|n| n.rem_euclid(4) as u64).collect();
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn test_client_server_inference() {
+        let (client_key, server_key) = keys();
+        let client = Client::new(client_key.clone());
+
+        let mut server = Server::new(server_key.as_ref().clone(), build_network!());
+        assert_eq!(server.modulus(), client.modulus());
+
+        // Only bytes are exchanged between the client and the server
+        let input: Matrix<i64> = Matrix::from_iter(vec![2], vec![1, -2], Layout::RowMajor);
+        let request = client.request(&input).to_bytes().unwrap();
+        let response = server.handle(&InferenceRequest::from_bytes(&request).unwrap()).unwrap().to_bytes().unwrap();
+        let output = client.decrypt_response(&InferenceResponse::from_bytes(&response).unwrap());
+
+        // The output is the same as the one of the unencrypted network, modulo 4
+        let mut plain_network: Nnet<i32> = build_network!();
+        let plain_result = plain_network.forward(Matrix::from_iter(vec![2], vec![1, -2], Layout::RowMajor)).unwrap();
+        let expected: Vec<i64> = plain_result.data.iter().map(|n| n.rem_euclid(4) as i64).collect();
+        assert_eq!(output.shape(), &vec![2]);
+        assert_eq!(output.data, expected);
+        assert_eq!(client.decrypt(&client.encrypt(&input)).data, vec![1, 2]);
+
+        // Inputs encrypted with another modulus are rejected
+        let mut other = client.encrypt(&input);
+        other.data[1].Modulus = Some(8);
+        assert_eq!(server.evaluate(&other).unwrap_err(), CryptoError::ModulusMismatch);
+        assert_eq!(InferenceRequest::from_bytes(&request[..10]).unwrap_err(), CryptoError::SerializationError);
+
+        // So are empty ciphertexts and ones without a modulus, before the network runs
+        let mut empty = client.encrypt(&input);
+        empty.data[0] = CipherTextType::default();
+        assert_eq!(server.evaluate(&empty).unwrap_err(), CryptoError::InvalidCiphertext);
+        let mut no_modulus = client.encrypt(&input);
+        no_modulus.data[0].Modulus = None;
+        assert_eq!(server.evaluate(&no_modulus).unwrap_err(), CryptoError::InvalidCiphertext);
+
+        // Errors of the network keep their cause
+        let wrong_shape = client.encrypt(&Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor));
+        let err = server.evaluate(&wrong_shape).unwrap_err();
+        assert!(matches!(&err, CryptoError::InferenceError(cause) if matches!(**cause, NnetError::ShapeMismatch { .. })));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_public_key_inference() {
+        let (client_key, server_key) = keys();
+        let client = Client::new(client_key.clone());
+        let public_key = key_store().get_or_generate_public_key("message_2_carry_2").unwrap();
+
+        // The public key goes through the envelope like the other keys
+        let bytes = key_to_bytes(&public_key, &public_key.parameters).unwrap();
+        let (loaded, parameters) = key_from_bytes::<CompressedPublicKey>(&bytes).unwrap();
+        assert_eq!((&loaded, parameters), (&public_key, PARAM_MESSAGE_2_CARRY_2));
+        assert_eq!(key_from_bytes::<ClientKey>(&bytes).unwrap_err(), CryptoError::SerializationError);
+
+        // A data source encrypts the input without the client key. Encryption with a compressed
+        // key takes seconds, so only one value is encrypted.
+        let encryptor = Encryptor::new(loaded);
+        assert_eq!(encryptor.modulus(), client.modulus());
+        let input: Matrix<i64> = Matrix::from_iter(vec![1], vec![-1], Layout::RowMajor);
+        let request = InferenceRequest::from_bytes(&encryptor.request(&input).to_bytes().unwrap()).unwrap();
+        assert_eq!(client.decrypt(&request.input).data, vec![3]);
+
+        // The server attaches its key and operates on it like on any other ciphertext
+        let mut value = request.input.data[0].clone();
+        value.set_server_key(server_key).unwrap();
+        assert_eq!(decrypt(&(value.clone() * encrypt(3))), 1);
+        assert_eq!(decrypt(&(value + encrypt(2))), 1);
+    }
+
+    #[test]
+    fn test_encrypted_activations() {
+        // The messages 0, 1, 2 and 3 are read as 0, 1, -2 and -1
+        let plain: Matrix<i32> = Matrix::from_iter(vec![2, 2], vec![0, 1, -2, -1], Layout::RowMajor);
+        let encrypted: Matrix<CipherTextType> = Matrix::from_iter(vec![2, 2], (0..4).map(encrypt), Layout::RowMajor);
+
+        let activations = [
+            Activation::Relu,
+            Activation::Sign,
+            Activation::ClippedRelu(0),
+            Activation::custom(|x| x.wrapping_mul(2).wrapping_add(1)),
+        ];
+        for activation in activations.iter() {
+            let expected: Vec<u64> = activate(&plain, activation).unwrap().data.iter().map(|n| n.rem_euclid(4) as u64).collect();
+            let result = activate(&encrypted, activation).unwrap();
+            assert_eq!(result.shape(), &vec![2, 2]);
+            assert_eq!(result.data.iter().map(decrypt).collect::<Vec<u64>>(), expected, "{activation:?}");
+        }
+
+        // The lookup table gets rid of the carries, so the input doesn't have to be clean
+        let sum = encrypt(3) + encrypt(3) + encrypt(1);
+        assert_eq!(decrypt(&sum.apply_lookup_table(|x| x).unwrap()), 3);
+        assert_eq!(decrypt(&sum.activate(&Activation::Relu).unwrap()), 0);
+
+        // Default values stay encrypted zeros, but custom tables need a key
+        assert!(CipherTextType::default().activate(&Activation::Relu).unwrap().is_def());
+        assert_eq!(CipherTextType::default().activate(&Activation::custom(|x| x + 1)).unwrap_err(), CryptoError::MissingKey);
+    }
+
+    #[test]
+    fn test_radix_relu() {
+        let (client_key, server_key) = keys();
+        // 4 bits read as two's complement, so 13 is -3
+        let values: Vec<RadixCipherText> = [5u64, 13, 0].iter()
+            .map(|value| RadixCipherText::encrypt(*value, 4, client_key, server_key).unwrap())
+            .collect();
+        let result = activate(&Matrix::from_iter(vec![3], values, Layout::RowMajor), &Activation::Relu).unwrap();
+        let decrypted: Vec<i64> = result.data.iter().map(|value| value.decrypt_signed(client_key).unwrap()).collect();
+        assert_eq!(decrypted, vec![5, 0, 0]);
+
+        // Lookup tables can't be evaluated on multiple blocks
+        let value = RadixCipherText::encrypt(3, 4, client_key, server_key).unwrap();
+        assert_eq!(value.activate(&Activation::Sign).unwrap_err(), CryptoError::UnsupportedActivation);
+    }
+
+    #[test]
+    fn test_radix_arithmetic() {
+        let (client_key, server_key) = keys();
+        let radix = |value: u64| RadixCipherText::encrypt(value, 8, client_key, server_key).unwrap();
+        let (lhs, rhs) = (radix(200), radix(100));
+        assert_eq!(lhs.num_blocks(), 4);
+        assert_eq!(lhs.num_bits(), 8);
+
+        // Everything is computed modulo 2^8 instead of the message modulus
+        assert_eq!((&lhs + &rhs).decrypt(client_key).unwrap(), 44);
+        assert_eq!((&lhs - &rhs).decrypt(client_key).unwrap(), 100);
+        assert_eq!((&rhs - &lhs).decrypt_signed(client_key).unwrap(), -100);
+        assert_eq!((radix(13) * radix(11)).decrypt(client_key).unwrap(), 143);
+        assert_eq!((&lhs * &rhs).decrypt(client_key).unwrap(), (200 * 100) % 256);
+        assert_eq!((-radix(1)).decrypt(client_key).unwrap(), 255);
+
+        // Scalars are reduced modulo 2^8 as well
+        assert_eq!((radix(7) * -3i32).decrypt_signed(client_key).unwrap(), -21);
+        assert_eq!((radix(7) * 300i32).decrypt(client_key).unwrap(), (7 * 300) % 256);
+        assert_eq!((radix(7) - 10i32).decrypt_signed(client_key).unwrap(), -3);
+        assert_eq!((250i32 + radix(7)).decrypt(client_key).unwrap(), 1);
+        assert_eq!(radix(7).checked_scalar_sub(i64::MIN).unwrap().decrypt(client_key).unwrap(), 7);
+
+        // A default value behaves like an encrypted zero
+        let default = RadixCipherText::default();
+        assert_eq!((&default + &lhs).decrypt(client_key).unwrap(), 200);
+        assert!((&default * &lhs).is_def());
+        assert_eq!(default.decrypt(client_key).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_radix_nnet_forward() {
+        let (client_key, server_key) = keys();
+        let mut plain_network: Nnet<i32> = build_network!();
+        let plain_result = plain_network.forward(Matrix::from_iter(vec![2], vec![5, -3], Layout::RowMajor)).unwrap();
+
+        // Unlike with CipherTextType, the results aren't reduced modulo 4
+        let mut encrypted_network: Nnet<RadixCipherText> = build_network!();
+        let input: Vec<RadixCipherText> = [5, -3].iter()
+            .map(|value: &i64| RadixCipherText::encrypt(*value as u64, 8, client_key, server_key).unwrap())
+            .collect();
+        let encrypted_result = encrypted_network.forward(Matrix::from_iter(vec![2], input, Layout::RowMajor)).unwrap();
+
+        let decrypted: Vec<i64> = encrypted_result.data.iter().map(|value| value.decrypt_signed(client_key).unwrap()).collect();
+        let expected: Vec<i64> = plain_result.data.iter().map(|n| *n as i64).collect();
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn test_radix_dense_layer_with_relu() {
+        let (client_key, server_key) = keys();
+        let weights = Matrix::from_iter(vec![2, 2], vec![1, -2, 2, 1], Layout::RowMajor);
+        let biases = Matrix::from_iter(vec![2], vec![0, -1], Layout::RowMajor);
+        let mut layer: DenseLayer<RadixCipherText> = DenseLayer::new("hidden", weights, biases, Some(Activation::Relu)).unwrap();
+
+        // [3 - 2, 6 + 1 - 1] and relu([1 - 6, 2 + 3 - 1])
+        for (input, expected) in [([3, 1], vec![1, 6]), ([1, 3], vec![0, 4])] {
+            let input: Vec<RadixCipherText> = input.iter()
+                .map(|value: &u64| RadixCipherText::encrypt(*value, 4, client_key, server_key).unwrap())
+                .collect();
+            let output = layer.forward(Matrix::from_iter(vec![2], input, Layout::RowMajor)).unwrap();
+            let decrypted: Vec<i64> = output.data.iter().map(|value| value.decrypt_signed(client_key).unwrap()).collect();
+            assert_eq!(decrypted, expected);
+        }
+    }
+
+    #[test]
+    fn test_encrypted_residual_block() {
+        // relu(W·x + b) + x, where the sum stays within the message modulus
+        let weights = Matrix::from_iter(vec![2, 2], vec![1, 0, 0, -1], Layout::RowMajor);
+        let biases = Matrix::from_iter(vec![2], vec![0, 1], Layout::RowMajor);
+        let mut network: Nnet<CipherTextType> = Nnet::new();
+        let layer = network.add_layer(LayerType::DenseLayer(DenseLayer::new("hidden", weights, biases, Some(Activation::Relu)).unwrap()));
+        let merge = network.add_merge(Merge::Add);
+        network.add_link(None, Some(layer)).unwrap();
+        network.add_link(None, Some(merge)).unwrap();
+        network.add_link(Some(layer), Some(merge)).unwrap();
+        network.add_link(Some(merge), None).unwrap();
+
+        let input = || Matrix::from_iter(vec![2], vec![encrypt(1), encrypt(0)], Layout::RowMajor);
+        let output = network.forward_multiple(vec![input(), input()]).unwrap();
+        // [relu(1) + 1, relu(-0 + 1) + 0]
+        let decrypted: Vec<u64> = output[0].data.iter().map(decrypt).collect();
+        assert_eq!(decrypted, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_if_encrypted_network_throws_error_when_ciphertexts_differ() {
+        // The layers and merge nodes return the errors of the ciphertexts instead of panicking
+        let mut other_modulus = encrypt(1);
+        other_modulus.Modulus = Some(8);
+        let inputs = vec![
+            Matrix::from_iter(vec![2], vec![encrypt(1), encrypt(2)], Layout::RowMajor),
+            Matrix::from_iter(vec![2], vec![other_modulus.clone(), encrypt(2)], Layout::RowMajor),
+        ];
+        let expected = NnetError::CryptoError(CryptoError::ModulusMismatch);
+        assert_eq!(Merge::Add.merge(inputs.clone()).err(), Some(expected.clone()));
+        assert_eq!(Merge::Multiply.merge(inputs).err(), Some(expected.clone()));
+
+        let mut network: Nnet<CipherTextType> = build_network!();
+        let input = Matrix::from_iter(vec![2], vec![other_modulus, encrypt(2)], Layout::RowMajor);
+        assert_eq!(network.forward(input).err(), Some(expected));
+
+        // A default value has no key to add the bias with
+        let weights = Matrix::from_iter(vec![1, 2], vec![1, 1], Layout::RowMajor);
+        let biases = Matrix::from_iter(vec![1], vec![1], Layout::RowMajor);
+        let mut layer: DenseLayer<CipherTextType> = DenseLayer::new("hidden", weights, biases, None).unwrap();
+        let input = Matrix::from_iter(vec![2], vec![CipherTextType::default(), CipherTextType::default()], Layout::RowMajor);
+        let err = layer.forward(input).unwrap_err();
+        assert_eq!(err, NnetError::CryptoError(CryptoError::MissingKey));
+        assert_eq!(err.to_string(), CryptoError::MissingKey.to_string());
+    }
+
+    #[test]
+    fn test_if_radix_throws_error_when_bit_widths_differ() {
+        let (client_key, server_key) = keys();
+        assert_eq!(RadixCipherText::encrypt(1, 7, client_key, server_key).unwrap_err(), CryptoError::InvalidBitWidth);
+        assert_eq!(RadixCipherText::encrypt(1, 0, client_key, server_key).unwrap_err(), CryptoError::InvalidBitWidth);
+        assert_eq!(RadixCipherText::encrypt(1, 66, client_key, server_key).unwrap_err(), CryptoError::InvalidBitWidth);
+
+        let lhs = RadixCipherText::encrypt(1, 4, client_key, server_key).unwrap();
+        let rhs = RadixCipherText::encrypt(1, 6, client_key, server_key).unwrap();
+        assert_eq!(lhs.checked_add(&rhs).unwrap_err(), CryptoError::ModulusMismatch);
+        assert_eq!(RadixCipherText::default().checked_scalar_add(1).unwrap_err(), CryptoError::MissingKey);
+
+        // The carry buffer has to be at least as big as the message buffer
+        let small_carry = ClientKey::new(tfhe::shortint::parameters::PARAM_MESSAGE_2_CARRY_1);
+        assert_eq!(RadixCipherText::encrypt(1, 4, &small_carry, server_key).unwrap_err(), CryptoError::UnsupportedParameters);
+    }
+
+    #[test]
+    fn test_parameter_catalog() {
+        for preset in PRESETS {
+            assert_eq!(find_preset(&preset.parameters).unwrap().name, preset.name);
+            assert_eq!(validate_parameters(&preset.parameters, SecurityPolicy::AllowInsecure), Ok(()));
+            let expected = if preset.is_secure() { Ok(()) } else { Err(CryptoError::InsecureParameters) };
+            assert_eq!(validate_parameters(&preset.parameters, SecurityPolicy::RequireSecure), expected);
+        }
+        assert_eq!(get_preset("message_3_carry_3").unwrap().carry_modulus(), 8);
+        assert!(get_preset("message_9_carry_9").is_none());
+
+        // Parameter sets which aren't in the catalog can't be checked
+        let mut unknown = PARAM_MESSAGE_2_CARRY_2;
+        unknown.lwe_dimension = LweDimension(300);
+        assert_eq!(custom_gen_keys(&unknown, SecurityPolicy::RequireSecure).unwrap_err(), CryptoError::InsecureParameters);
+
+        // The keys are generated with the requested parameters
+        let (client_key, _server_key) = custom_gen_keys(&MY_PARAM, SecurityPolicy::AllowInsecure).unwrap();
+        assert_eq!(client_key.parameters, MY_PARAM);
+    }
+
+    #[test]
+    fn test_default_ciphertext() {
+        let value = encrypt(3);
+        let default = CipherTextType::default();
+        assert!(default.is_def());
+        assert!(!value.is_def());
+
+        // A default value behaves like an encrypted zero
+        assert_eq!(decrypt(&(default.clone() + value.clone())), 3);
+        assert_eq!(decrypt(&(value.clone() - default.clone())), 3);
+        assert_eq!(decrypt(&(default.clone() - value.clone())), 1);
+        assert!((default.clone() * value.clone()).is_def());
+        assert_eq!(decrypt(&default), 0);
+        assert_eq!(default.checked_scalar_add(1).unwrap_err(), CryptoError::MissingKey);
+
+        // Clones hold the same ciphertext and share the server key
+        assert_eq!(decrypt(&value.clone()), 3);
+        let result = value.clone() * value.clone() + value.clone();
+        assert!(Arc::ptr_eq(result.ServerKey.as_ref().unwrap(), &keys().1));
+    }
+
+    #[test]
+    fn test_if_ciphertext_ops_throw_error_when_keys_or_moduli_differ() {
+        let (_, server_key) = keys();
+        let value = encrypt(1);
+        let other = CipherTextType::new(value.CipherTxt.clone().unwrap(), server_key.clone(), 8);
+        assert_eq!(value.checked_add(&other).unwrap_err(), CryptoError::ModulusMismatch);
+        assert_eq!(value.checked_mul(&other).unwrap_err(), CryptoError::ModulusMismatch);
+
+        // Ciphertexts encrypted under other keys can't be mixed in
+        let (other_client_key, other_server_key) = key_store()
+            .get_or_generate("other_message_2_carry_2", &PARAM_MESSAGE_2_CARRY_2, SecurityPolicy::RequireSecure)
+            .unwrap();
+        let other = CipherTextType::encrypt(1, &other_client_key, &Arc::new(other_server_key));
+        assert_eq!(value.checked_sub(&other).unwrap_err(), CryptoError::KeyMismatch);
+
+        let mut without_key = value.clone();
+        without_key.ServerKey = None;
+        assert_eq!(without_key.checked_neg().unwrap_err(), CryptoError::MissingKey);
+    }
+
+    #[test]
+    fn test_key_store() {
+        let (client_key, server_key) = keys();
+        let store = key_store();
+
+        // Keys which were saved decrypt and evaluate exactly like the originals
+        store.save("round_trip", client_key, server_key).unwrap();
+        assert!(store.contains("round_trip"));
+        let (loaded_client_key, loaded_server_key) = store.load("round_trip").unwrap();
+        assert_eq!(&loaded_server_key, server_key.as_ref());
+        let value = CipherTextType::encrypt(3, &loaded_client_key, &Arc::new(loaded_server_key));
+        assert_eq!(decrypt(&value), 3);
+        assert_eq!(decrypt(&(encrypt(2) * encrypt(3))), 2);
+
+        // The stored parameters have to match the requested ones
+        assert_eq!(
+            store.get_or_generate("round_trip", &PARAM_MESSAGE_3_CARRY_3, SecurityPolicy::RequireSecure).unwrap_err(),
+            CryptoError::ParameterMismatch
+        );
+
+        // Names can't point outside of the store
+        assert!(!store.contains("../round_trip"));
+        assert_eq!(store.load_client_key("../round_trip").unwrap_err(), CryptoError::InvalidKeyName);
+        assert_eq!(store.load_server_key("missing").unwrap_err(), CryptoError::IoError);
+    }
+
+    #[test]
+    fn test_if_key_from_bytes_throws_error_when_envelope_is_invalid() {
+        let (client_key, _) = keys();
+        let bytes = key_to_bytes(client_key, &client_key.parameters).unwrap();
+        let (loaded, parameters) = key_from_bytes::<ClientKey>(&bytes).unwrap();
+        assert_eq!(parameters, PARAM_MESSAGE_2_CARRY_2);
+        assert_eq!(loaded.parameters, client_key.parameters);
+
+        // A client key can't be loaded as a server key
+        assert_eq!(key_from_bytes::<ServerKey>(&bytes).unwrap_err(), CryptoError::SerializationError);
+
+        // Neither can files of another format version
+        let mut other_version = bytes.clone();
+        other_version[4] = KEY_FORMAT_VERSION + 1;
+        assert_eq!(key_from_bytes::<ClientKey>(&other_version).unwrap_err(), CryptoError::SerializationError);
+
+        // Or truncated ones
+        assert_eq!(key_from_bytes::<ClientKey>(&bytes[..bytes.len() / 2]).unwrap_err(), CryptoError::SerializationError);
+        assert_eq!(key_from_bytes::<ClientKey>(&bytes[..3]).unwrap_err(), CryptoError::SerializationError);
     }
 }
 
 
+#[cfg(test)]
+mod test_neural_network {
+    use Cryptonic::neural_network::activations::Activation;
+    use Cryptonic::neural_network::dense_layer::DenseLayer;
+    use Cryptonic::neural_network::errors::NnetError;
+    use Cryptonic::neural_network::layer_trait::Layer;
+    use Cryptonic::neural_network::layer_type::LayerType;
+    use Cryptonic::neural_network::merge::Merge;
+    use Cryptonic::neural_network::nnet::Nnet;
+    use Cryptonic::neural_network::sequential::Sequential;
+    use Cryptonic::tensor_library::errors::MatrixError;
+    use Cryptonic::tensor_library::layout::Layout;
+    use Cryptonic::tensor_library::matrix::Matrix;
+
+    fn dense_layer(weights: Vec<i32>, biases: Vec<i32>, activation: Option<Activation>) -> DenseLayer<i32> {
+        let (outputs, inputs) = (biases.len(), weights.len() / biases.len());
+        let weights = Matrix::from_iter(vec![outputs, inputs], weights, Layout::RowMajor);
+        let biases = Matrix::from_iter(vec![outputs], biases, Layout::RowMajor);
+        DenseLayer::new("dense", weights, biases, activation).unwrap()
+    }
+
+    #[test]
+    fn test_dense_layer_forward() {
+        // 3 inputs and 2 outputs
+        let mut layer = dense_layer(vec![1, 0, 2, -1, 1, 0], vec![1, -4], None);
+        assert_eq!(layer.get_input_shape(), &vec![3]);
+        assert_eq!(layer.get_output_shape(), &vec![2]);
+        assert_eq!(layer.weights().shape(), &vec![2, 3]);
+        assert!(layer.activation().is_none());
+
+        let result = layer.forward(Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor)).unwrap();
+        assert_eq!(result.shape(), &vec![2]);
+        assert_eq!(result.data, vec![8, -3]);
+
+        let mut layer = dense_layer(vec![1, 0, 2, -1, 1, 0], vec![1, -4], Some(Activation::Relu));
+        let result = layer.forward(Matrix::from_iter(vec![3], vec![1, 2, 3], Layout::RowMajor)).unwrap();
+        assert_eq!(result.data, vec![8, 0]);
+
+        let mut layer = dense_layer(vec![3, -2], vec![0], Some(Activation::ClippedRelu(5)));
+        let result = layer.forward(Matrix::from_iter(vec![2], vec![4, 1], Layout::RowMajor)).unwrap();
+        assert_eq!(result.data, vec![5]);
+    }
+
+    #[test]
+    fn test_dense_layer_forward_batch() {
+        let mut layer = dense_layer(vec![1, 0, 2, -1, 1, 0], vec![1, -4], None);
+        let batch = Matrix::from_iter(vec![2, 3], vec![1, 2, 3, 0, 1, -1], Layout::RowMajor);
+        let result = layer.forward(batch).unwrap();
+        assert_eq!(result.shape(), &vec![2, 2]);
+        assert_eq!(result.data, vec![8, -3, -1, -3]);
+
+        // Every row of a transposed batch is a sample as well
+        let mut batch = Matrix::from_iter(vec![3, 2], vec![1, 0, 2, 1, 3, -1], Layout::RowMajor);
+        batch.transpose();
+        assert_eq!(layer.forward(batch).unwrap().data, vec![8, -3, -1, -3]);
+
+        let expected = NnetError::ShapeMismatch { layer: "dense".to_string(), expected: vec![3], found: vec![1, 2, 3] };
+        assert_eq!(layer.forward(Matrix::new(vec![1, 2, 3], Layout::RowMajor)).err(), Some(expected));
+        let expected = NnetError::ShapeMismatch { layer: "dense".to_string(), expected: vec![3], found: vec![3, 2] };
+        assert_eq!(layer.forward(Matrix::new(vec![3, 2], Layout::RowMajor)).err(), Some(expected));
+    }
+
+    #[test]
+    fn test_if_dense_layer_throws_error_when_shapes_differ() {
+        let weights: Matrix<i32> = Matrix::from_iter(vec![2, 3], 1.., Layout::RowMajor);
+        let biases: Matrix<i32> = Matrix::from_iter(vec![3], 1.., Layout::RowMajor);
+        assert_eq!(DenseLayer::<i32>::new("hidden", weights.clone(), biases, None).err(), Some(NnetError::WeightCountMismatch { layer: "hidden".to_string() }));
+
+        let flat_weights: Matrix<i32> = Matrix::from_iter(vec![6], 1.., Layout::RowMajor);
+        let biases: Matrix<i32> = Matrix::from_iter(vec![2], 1.., Layout::RowMajor);
+        assert_eq!(DenseLayer::<i32>::new("hidden", flat_weights, biases.clone(), None).err(), Some(NnetError::WeightCountMismatch { layer: "hidden".to_string() }));
+
+        let mut layer: DenseLayer<i32> = DenseLayer::new("hidden", weights, biases, None).unwrap();
+        assert_eq!(layer.name(), "hidden");
+        let expected = NnetError::ShapeMismatch { layer: "hidden".to_string(), expected: vec![3], found: vec![2] };
+        assert_eq!(layer.forward(Matrix::from_iter(vec![2], vec![1, 2], Layout::RowMajor)).err(), Some(expected));
+    }
+
+    // Layer with 2 inputs and 2 outputs, which adds the given offset to both
+    fn offset_layer(offset: i32) -> LayerType<i32> {
+        LayerType::DenseLayer(dense_layer(vec![1, 0, 0, 1], vec![offset, offset], None))
+    }
+
+    fn vector(data: Vec<i32>) -> Matrix<i32> {
+        Matrix::from_iter(vec![data.len()], data, Layout::RowMajor)
+    }
+
+    #[test]
+    fn test_network_runs_in_topological_order() {
+        // The layers are added in a different order than the one they run in
+        let mut network: Nnet<i32> = Nnet::new();
+        let last = network.add_layer(offset_layer(100));
+        let first = network.add_layer(offset_layer(1));
+        let middle = network.add_layer(LayerType::DenseLayer(dense_layer(vec![2, 0, 0, 2], vec![0, 0], None)));
+        network.add_link(Some(last), None).unwrap();
+        network.add_link(Some(middle), Some(last)).unwrap();
+        network.add_link(Some(first), Some(middle)).unwrap();
+        network.add_link(None, Some(first)).unwrap();
+
+        assert_eq!(network.get_input_layer_ids(), vec![first]);
+        assert_eq!(network.get_output_layer_ids(), vec![last]);
+        assert_eq!(network.topological_order().unwrap(), vec![first, middle, last]);
+        // (x + 1) * 2 + 100
+        assert_eq!(network.forward(vector(vec![1, 2])).unwrap().data, vec![104, 106]);
+    }
+
+    #[test]
+    fn test_network_with_multiple_inputs_and_outputs() {
+        // One branch per input, where the second one also goes through a shared layer, whose
+        // output is used twice
+        let mut network: Nnet<i32> = Nnet::new();
+        let ids: Vec<usize> = [1, 10, 100, 1000].into_iter().map(|offset| network.add_layer(offset_layer(offset))).collect();
+        network.add_link(None, Some(ids[0])).unwrap();
+        network.add_link(None, Some(ids[1])).unwrap();
+        network.add_link(Some(ids[1]), Some(ids[2])).unwrap();
+        network.add_link(Some(ids[1]), Some(ids[3])).unwrap();
+        network.add_link(Some(ids[3]), None).unwrap();
+        network.add_link(Some(ids[0]), None).unwrap();
+        network.add_link(Some(ids[2]), None).unwrap();
+
+        assert_eq!(network.topological_order().unwrap(), vec![ids[0], ids[1], ids[2], ids[3]]);
+        let outputs = network.forward_multiple(vec![vector(vec![0, 0]), vector(vec![5, 6])]).unwrap();
+        let outputs: Vec<Vec<i32>> = outputs.into_iter().map(|output| output.data).collect();
+        assert_eq!(outputs, vec![vec![1015, 1016], vec![1, 1], vec![115, 116]]);
+
+        // The single input and output version can't decide which ones to use
+        assert!(network.forward(vector(vec![0, 0])).is_err());
+        assert!(network.forward_multiple(vec![vector(vec![0, 0])]).is_err());
+
+        // Running the network again gives the same result
+        let outputs = network.forward_multiple(vec![vector(vec![0, 0]), vector(vec![5, 6])]).unwrap();
+        assert_eq!(outputs[0].data, vec![1015, 1016]);
+    }
+
+    #[test]
+    fn test_if_network_throws_error_on_invalid_graph() {
+        // Cycle
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let second = network.add_layer(offset_layer(2));
+        let third = network.add_layer(offset_layer(3));
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), Some(second)).unwrap();
+        network.add_link(Some(second), Some(third)).unwrap();
+        network.add_link(Some(third), Some(second)).unwrap();
+        network.add_link(Some(third), None).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::TooManyInputLinks { layer: second.to_string() }));
+
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let second = network.add_layer(offset_layer(2));
+        let third = network.add_layer(offset_layer(3));
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), None).unwrap();
+        network.add_link(Some(second), Some(third)).unwrap();
+        network.add_link(Some(third), Some(second)).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::Cycle));
+        assert_eq!(network.forward(vector(vec![1, 2])).err(), Some(NnetError::Cycle));
+
+        // Layer without links to it
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let dangling = network.add_layer(offset_layer(2));
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), None).unwrap();
+        network.add_link(Some(dangling), None).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingInputLink { layer: dangling.to_string() }));
+
+        // Layer whose output isn't used
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let dangling = network.add_layer(offset_layer(2));
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), None).unwrap();
+        network.add_link(Some(first), Some(dangling)).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingOutputLink { layer: dangling.to_string() }));
+
+        // No inputs or outputs
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingNetworkInput));
+        network.add_link(None, Some(first)).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingNetworkOutput));
+        network.add_link(Some(first), None).unwrap();
+        assert_eq!(network.topological_order().unwrap(), vec![first]);
+
+        // Wrong input shape
+        let expected = NnetError::ShapeMismatch { layer: first.to_string(), expected: vec![2], found: vec![3] };
+        assert_eq!(network.forward(vector(vec![1, 2, 3])).err(), Some(expected));
+
+        // Links between unknown layers or between None and None
+        assert_eq!(network.add_link(Some(first), Some(7)).err(), Some(NnetError::UnknownLayer(7)));
+        assert_eq!(network.add_link(Some(7), None).err(), Some(NnetError::UnknownLayer(7)));
+        assert_eq!(network.add_link(None, None).err(), Some(NnetError::InvalidLink));
+        let smaller = network.add_layer(LayerType::DenseLayer(dense_layer(vec![1, 1, 1], vec![0], None)));
+        let expected = NnetError::ShapeMismatch { layer: smaller.to_string(), expected: vec![3], found: vec![2] };
+        assert_eq!(network.add_link(Some(first), Some(smaller)).err(), Some(expected));
+    }
+
+    #[test]
+    fn test_network_with_merge_nodes() {
+        // Residual block: x + layer(x), where x is the output of the first layer
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let second = network.add_layer(LayerType::DenseLayer(dense_layer(vec![2, 0, 0, 3], vec![0, 0], None)));
+        let residual = network.add_merge(Merge::Add);
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), Some(second)).unwrap();
+        network.add_link(Some(first), Some(residual)).unwrap();
+        network.add_link(Some(second), Some(residual)).unwrap();
+        network.add_link(Some(residual), None).unwrap();
+        assert_eq!(network.topological_order().unwrap(), vec![first, second, residual]);
+        // [2 + 4, 3 + 9]
+        assert_eq!(network.forward(vector(vec![1, 2])).unwrap().data, vec![6, 12]);
+
+        // Two branches, which get concatenated and multiplied with the input of the network
+        let mut network: Nnet<i32> = Nnet::new();
+        let concat = network.add_merge(Merge::Concat(0));
+        let multiply = network.add_merge(Merge::Multiply);
+        let left = network.add_layer(LayerType::DenseLayer(dense_layer(vec![1, 1], vec![0], None)));
+        let right = network.add_layer(LayerType::DenseLayer(dense_layer(vec![1, -1], vec![0], None)));
+        let last = network.add_layer(LayerType::DenseLayer(dense_layer(vec![1, 0, 0, 1], vec![0, 0], None)));
+        network.add_link(None, Some(left)).unwrap();
+        network.add_link(None, Some(right)).unwrap();
+        network.add_link(Some(right), Some(concat)).unwrap();
+        network.add_link(Some(left), Some(concat)).unwrap();
+        network.add_link(Some(concat), Some(multiply)).unwrap();
+        network.add_link(None, Some(multiply)).unwrap();
+        network.add_link(Some(multiply), Some(last)).unwrap();
+        network.add_link(Some(last), None).unwrap();
+        assert_eq!(network.topological_order().unwrap(), vec![left, right, concat, multiply, last]);
+
+        let inputs = vec![vector(vec![5, 3]), vector(vec![5, 3]), vector(vec![10, 100])];
+        // [5 - 3, 5 + 3] * [10, 100]
+        assert_eq!(network.forward_multiple(inputs).unwrap()[0].data, vec![20, 800]);
+
+        // A merge node can take the same node twice, e.g. to square it, other links can only be
+        // added once
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let square = network.add_merge(Merge::Multiply);
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), Some(square)).unwrap();
+        network.add_link(Some(first), Some(square)).unwrap();
+        network.add_link(Some(square), None).unwrap();
+        assert_eq!(network.topological_order().unwrap(), vec![first, square]);
+        assert_eq!(network.forward(vector(vec![1, 2])).unwrap().data, vec![4, 9]);
+        assert_eq!(network.add_link(None, Some(first)).err(), Some(NnetError::DuplicateLink));
+        assert_eq!(network.add_link(Some(square), None).err(), Some(NnetError::DuplicateLink));
+
+        // The same goes for the input of the network, which the merge node then gets twice
+        let mut network: Nnet<i32> = Nnet::new();
+        let double = network.add_merge(Merge::Add);
+        network.add_link(None, Some(double)).unwrap();
+        network.add_link(None, Some(double)).unwrap();
+        network.add_link(Some(double), None).unwrap();
+        assert_eq!(network.forward_multiple(vec![vector(vec![1, 2]), vector(vec![10, 20])]).unwrap()[0].data, vec![11, 22]);
+    }
+
+    #[test]
+    fn test_if_merge_throws_error_when_shapes_differ() {
+        let (short, long) = (vector(vec![1, 2]), vector(vec![1, 2, 3]));
+        assert_eq!(Merge::Add.merge(vec![short.clone(), long.clone()]).err(), Some(NnetError::MatrixError(MatrixError::DimError)));
+        assert!(Merge::Multiply.merge(vec![short.clone(), long.clone()]).is_err());
+        assert!(Merge::Concat(1).merge(vec![short.clone(), long.clone()]).is_err());
+        assert!(Merge::Add.merge(Vec::<Matrix<i32>>::new()).is_err());
+        assert_eq!(Merge::Concat(0).merge(vec![short.clone(), long]).unwrap().data, vec![1, 2, 1, 2, 3]);
+
+        // Merging the outputs of layers with different shapes fails when the network runs
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let second = network.add_layer(LayerType::DenseLayer(dense_layer(vec![1, 1], vec![0], None)));
+        let merge = network.add_merge(Merge::Add);
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(None, Some(second)).unwrap();
+        network.add_link(Some(first), Some(merge)).unwrap();
+        network.add_link(Some(second), Some(merge)).unwrap();
+        network.add_link(Some(merge), None).unwrap();
+        assert!(network.topological_order().is_ok());
+        assert!(network.forward_multiple(vec![short.clone(), short]).is_err());
+
+        // A merge node without links to it
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let merge = network.add_merge(Merge::Add);
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), None).unwrap();
+        network.add_link(Some(merge), None).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingInputLink { layer: merge.to_string() }));
+    }
+
+    #[test]
+    fn test_sequential_builder() {
+        let mut network: Nnet<i32> = Sequential::new()
+            .input(3)
+            .dense(2).weights(vec![vec![1, 0, 2], vec![-1, 1, 0]]).biases(vec![1, -4]).relu()
+            .dense(2).weights(vec![vec![1, 1], vec![2, -1]]).biases(vec![0, 0]).activation(Activation::ClippedRelu(10))
+            .dense(1).weights(vec![vec![1, 1]]).biases(vec![-3]).name("output")
+            .build()
+            .unwrap();
+        assert_eq!(network.get_input_layer_ids().len(), 1);
+        assert_eq!(network.topological_order().unwrap().len(), 3);
+
+        // relu([8, -3]) = [8, 0], clipped_relu([8, 16]) = [8, 10] and 8 + 10 - 3
+        let result = network.forward(vector(vec![1, 2, 3])).unwrap();
+        assert_eq!(result.shape(), &vec![1]);
+        assert_eq!(result.data, vec![15]);
+
+        // The number of inputs of every layer is the number of outputs of the previous one
+        let mut network: Nnet<i32> = Sequential::new()
+            .input(3)
+            .dense(2).weights(vec![vec![1, 0, 2], vec![-1, 1, 0]]).biases(vec![1, -4]).relu()
+            .dense(2).weights(vec![vec![1, 1], vec![2, -1]]).biases(vec![0, 0]).activation(Activation::ClippedRelu(10))
+            .build()
+            .unwrap();
+        assert_eq!(network.forward(vector(vec![1, 2, 3])).unwrap().data, vec![8, 10]);
+    }
+
+    #[test]
+    fn test_if_sequential_builder_throws_error_with_layer_name() {
+        let result = Sequential::<i32>::new().dense(2).weights(vec![vec![1, 2], vec![3, 4]]).biases(vec![0, 0]).build();
+        assert_eq!(result.err(), Some(NnetError::MissingInputSize));
+
+        // Layers don't get zeros as their weights or biases
+        let result = Sequential::<i32>::new().input(2).dense(2).name("hidden").biases(vec![0, 0]).build();
+        let error = result.err().unwrap();
+        assert_eq!(error, NnetError::MissingWeights { layer: "hidden".to_string() });
+        assert_eq!(error.to_string(), "Layer hidden doesn't have weights or biases");
+        let result = Sequential::<i32>::new().input(2).dense(1).weights(vec![vec![1, 2]]).biases(vec![0]).dense(1).weights(vec![vec![1]]).build();
+        assert_eq!(result.err(), Some(NnetError::MissingWeights { layer: "dense_1".to_string() }));
+
+        // The weights have to fit the number of outputs of the previous layer
+        let result = Sequential::<i32>::new()
+            .input(3)
+            .dense(2).weights(vec![vec![1, 0, 2], vec![-1, 1, 0]]).biases(vec![1, -4])
+            .dense(1).weights(vec![vec![1, 1, 1]]).biases(vec![0]).name("output")
+            .build();
+        assert_eq!(result.err(), Some(NnetError::WeightCountMismatch { layer: "output".to_string() }));
+        let result = Sequential::<i32>::new().input(2).dense(2).name("hidden").weights(vec![vec![1, 2], vec![3]]).biases(vec![0, 0]).build();
+        assert_eq!(result.err(), Some(NnetError::WeightCountMismatch { layer: "hidden".to_string() }));
+        let result = Sequential::<i32>::new().input(2).dense(2).weights(vec![vec![1, 2]]).biases(vec![0, 0]).build();
+        assert_eq!(result.err(), Some(NnetError::WeightCountMismatch { layer: "dense_0".to_string() }));
+        let result = Sequential::<i32>::new().input(2).dense(2).weights(vec![vec![1, 2], vec![3, 4]]).biases(vec![1, 2, 3]).build();
+        assert_eq!(result.err(), Some(NnetError::WeightCountMismatch { layer: "dense_0".to_string() }));
+
+        assert_eq!(Sequential::<i32>::new().input(2).build().err(), Some(NnetError::EmptyModel));
+        assert_eq!(Sequential::<i32>::new().relu().input(2).dense(2).build().err(), Some(NnetError::MissingLayer));
+
+        // The built network keeps the names for the errors when it runs
+        let mut network: Nnet<i32> = Sequential::new()
+            .input(3)
+            .dense(2).weights(vec![vec![1, 0, 2], vec![-1, 1, 0]]).biases(vec![1, -4]).name("input")
+            .dense(1).weights(vec![vec![1, 1]]).biases(vec![0])
+            .build()
+            .unwrap();
+        assert_eq!(network.get_name(0), "input");
+        assert_eq!(network.get_name(1), "dense_1");
+        let expected = NnetError::ShapeMismatch { layer: "input".to_string(), expected: vec![3], found: vec![2] };
+        assert_eq!(network.forward(vector(vec![1, 2])).err(), Some(expected));
+
+        // Nodes of a network without names are called by their id
+        let mut network: Nnet<i32> = Nnet::new();
+        let first = network.add_layer(offset_layer(1));
+        let merge = network.add_merge(Merge::Add);
+        network.add_link(None, Some(first)).unwrap();
+        network.add_link(Some(first), None).unwrap();
+        network.add_link(Some(merge), None).unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingInputLink { layer: merge.to_string() }));
+        network.set_name(merge, "residual").unwrap();
+        assert_eq!(network.topological_order().err(), Some(NnetError::MissingInputLink { layer: "residual".to_string() }));
+        assert_eq!(network.set_name(7, "unknown").err(), Some(NnetError::UnknownLayer(7)));
+
+        // Errors of the matrices and activations keep their cause
+        let error = NnetError::from(MatrixError::MatmulShapeError);
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(std::error::Error::source(&NnetError::Cycle).is_none());
+    }
+}
 
 /*
 #[cfg(test)]